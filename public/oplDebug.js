@@ -0,0 +1,123 @@
+// Shadow of the OPL registers for the debug view of the player. Only a
+// player built with the `debug` feature loads this module into the
+// worklet, before oplProcessor.js (see index.html). The generator keeps
+// its registers to itself, so the writes are replayed from the played IMF
+// and AdLib data.
+const DEBUG_SOURCE_ENGINE = 0;
+const DEBUG_SOURCE_MUSIC = 1;
+const DEBUG_SOURCE_SFX = 2;
+// blocks between two debug messages, about 20 per second
+const DEBUG_POST_BLOCKS = 18;
+const DEBUG_MAX_WRITES = 256;
+
+class OPLDebug {
+  constructor(mixerRate, imfClockRate, adlClockRate) {
+    this.imfTicksPerFrame = imfClockRate / mixerRate;
+    this.adlTicksPerFrame = adlClockRate / mixerRate;
+    this.regs = new Uint8Array(256);
+    this.writes = [];
+    this.blocks = 0;
+    this.imf = null;
+    this.adl = null;
+  }
+
+  // a register written by the engine itself
+  writeEngine(reg, value) {
+    this.write(reg, value, DEBUG_SOURCE_ENGINE);
+  }
+
+  write(reg, value, source) {
+    this.regs[reg & 0xff] = value;
+    if (this.writes.length < DEBUG_MAX_WRITES) {
+      this.writes.push([reg, value, source]);
+    }
+  }
+
+  playImf(bytes) {
+    this.imf = { data: bytes.slice(), pos: 0, wait: 0, ticks: 0 };
+  }
+
+  stopImf() {
+    this.imf = null;
+  }
+
+  playAdl(bytes) {
+    const view = new DataView(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+    const length = view.getUint32(0, true);
+    const inst = bytes.slice(6, 22);
+    // as the original sound driver: key off, instrument on channel 0
+    this.write(0xb0, 0, DEBUG_SOURCE_SFX);
+    const regs = [0x20, 0x40, 0x60, 0x80, 0xe0];
+    for (let i = 0; i < regs.length; i++) {
+      this.write(regs[i], inst[i * 2], DEBUG_SOURCE_SFX);
+      this.write(regs[i] + 3, inst[i * 2 + 1], DEBUG_SOURCE_SFX);
+    }
+    this.write(0xc0, 0, DEBUG_SOURCE_SFX);
+    this.adl = {
+      notes: bytes.slice(23, 23 + length),
+      block: ((bytes[22] & 7) << 2) | 0x20,
+      pos: 0,
+      ticks: 0,
+    };
+  }
+
+  advance(frames, musicPaused) {
+    const imf = this.imf;
+    if (imf && !musicPaused && imf.data.length >= 4) {
+      imf.ticks += frames * this.imfTicksPerFrame;
+      while (imf.ticks >= imf.wait) {
+        imf.ticks -= imf.wait;
+        if (imf.pos + 4 > imf.data.length) {
+          // the music loops, like the generator one tick after the last
+          // command (the data may have no delays at all)
+          imf.pos = 0;
+          imf.wait = 1;
+          continue;
+        }
+        const d = imf.data;
+        this.write(d[imf.pos], d[imf.pos + 1], DEBUG_SOURCE_MUSIC);
+        imf.wait = d[imf.pos + 2] | (d[imf.pos + 3] << 8);
+        imf.pos += 4;
+      }
+    }
+
+    const adl = this.adl;
+    if (adl) {
+      adl.ticks += frames * this.adlTicksPerFrame;
+      while (adl.ticks >= 1 && adl) {
+        adl.ticks -= 1;
+        if (adl.pos >= adl.notes.length) {
+          this.write(0xb0, 0, DEBUG_SOURCE_SFX);
+          this.adl = null;
+          break;
+        }
+        const note = adl.notes[adl.pos++];
+        if (note) {
+          this.write(0xa0, note, DEBUG_SOURCE_SFX);
+          this.write(0xb0, adl.block, DEBUG_SOURCE_SFX);
+        } else {
+          this.write(0xb0, 0, DEBUG_SOURCE_SFX);
+        }
+      }
+    }
+  }
+
+  // called for every generated block with the final output
+  block(output, musicPaused, port) {
+    this.advance(output[0].length, musicPaused);
+    this.blocks += 1;
+    if (this.blocks < DEBUG_POST_BLOCKS) {
+      return;
+    }
+    this.blocks = 0;
+    const scope = new Float32Array(output[0].length);
+    for (let i = 0; i < scope.length; i++) {
+      scope[i] = (output[0][i] + output[1][i]) / 2;
+    }
+    port.postMessage({ type: "opl_debug", regs: this.regs.slice(), scope, writes: this.writes });
+    this.writes = [];
+  }
+}
+
+// worklet modules share the global scope, not their declarations
+globalThis.OPLDebug = OPLDebug;
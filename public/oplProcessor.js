@@ -6,13 +6,23 @@ class OPLProcessor extends AudioWorkletProcessor {
     this.adl_data_ptr = 0;
     this.adl_data_len = 0;
     this.adl_playing = false;
+    this.music_volume = 1.0;
+    this.sfx_volume = 1.0;
+    this.muted = false;
+    this.music_paused = false;
+    this.silence = new Float32Array(256);
+    this.debug = null;
 
     const { wasmBytes, mixerRate, imfClockRate, adlClockRate } = options.processorOptions;
     const module = new WebAssembly.Module(wasmBytes);
-    const instance = new WebAssembly.Instance(module, {});
-    this.wasm = instance.exports;
+    // music and sound effects get a chip each, so they can be mixed
+    // with their own volume
+    this.wasm = new WebAssembly.Instance(module, {}).exports;
+    this.sfx_wasm = new WebAssembly.Instance(module, {}).exports;
 
     this.generatorPtr = this.wasm.new_generator(mixerRate, imfClockRate, adlClockRate);
+    this.sfxGeneratorPtr = this.sfx_wasm.new_generator(mixerRate, imfClockRate, adlClockRate);
+    this.clockRates = [mixerRate, imfClockRate, adlClockRate];
 
     this.port.onmessage = (event) => {
       if (event.data.cmd === "play_imf") {
@@ -27,36 +37,73 @@ class OPLProcessor extends AudioWorkletProcessor {
         let ptr_bytes = new Uint8Array(this.wasm.memory.buffer, this.imf_data_ptr, this.imf_data_len);
         ptr_bytes.set(bytes);
         this.wasm.play_imf(this.generatorPtr, this.imf_data_ptr, this.imf_data_len);
+        if (this.debug) {
+          this.debug.playImf(bytes);
+        }
       } else if (event.data.cmd === "play_adl") {
         if (this.adl_data_ptr) {
-          this.wasm.dealloc(this.adl_data_ptr, this.adl_data_len);
+          this.sfx_wasm.dealloc(this.adl_data_ptr, this.adl_data_len);
         }
         let bytes = event.data.data;
         this.adl_data_len = bytes.length;
-        this.adl_data_ptr = this.wasm.alloc(this.adl_data_len);
-        let ptr_bytes = new Uint8Array(this.wasm.memory.buffer, this.adl_data_ptr, this.adl_data_len);
+        this.adl_data_ptr = this.sfx_wasm.alloc(this.adl_data_len);
+        let ptr_bytes = new Uint8Array(this.sfx_wasm.memory.buffer, this.adl_data_ptr, this.adl_data_len);
         ptr_bytes.set(bytes);
-        this.wasm.play_adl(this.generatorPtr, this.adl_data_ptr, this.adl_data_len);
+        this.sfx_wasm.play_adl(this.sfxGeneratorPtr, this.adl_data_ptr, this.adl_data_len);
         this.adl_playing = true;
+        if (this.debug) {
+          this.debug.playAdl(bytes);
+        }
       } else if (event.data.cmd === "write_reg") {
-        this.wasm.write_reg(event.data.reg, event.data.value);
+        // the engine only writes registers to silence the music when it
+        // stops a track, the sound effects chip is left alone
+        this.wasm.write_reg(this.generatorPtr, event.data.reg, event.data.value);
+        if (this.debug) {
+          this.debug.writeEngine(event.data.reg, event.data.value);
+        }
       } else if (event.data.cmd === "stop_imf") {
-        this.wasm.stop_imf();
+        this.wasm.stop_imf(this.generatorPtr);
+        if (this.debug) {
+          this.debug.stopImf();
+        }
+      } else if (event.data.cmd === "pause_imf") {
+        // sent by the jukebox of the player
+        this.music_paused = event.data.paused;
+      } else if (event.data.cmd === "debug") {
+        // sent by the debug view of the player, oplDebug.js is loaded
+        // with it
+        const OPLDebug = globalThis.OPLDebug;
+        this.debug = event.data.enabled && OPLDebug ? new OPLDebug(...this.clockRates) : null;
+      } else if (event.data.cmd === "set_volume") {
+        // sent by the player (src/audio.rs)
+        this.music_volume = event.data.music;
+        this.sfx_volume = event.data.sfx;
+        this.muted = event.data.muted;
       }
     };
   }
 
   process(inputs, outputs) {
-    const ptr = this.wasm.generate_block(this.generatorPtr);
-    const bytes = new Float32Array(this.wasm.memory.buffer, ptr, 256);
+    // a paused generator keeps its position in the music
+    const music = this.music_paused
+      ? this.silence
+      : new Float32Array(this.wasm.memory.buffer, this.wasm.generate_block(this.generatorPtr), 256);
+    const sfx_ptr = this.sfx_wasm.generate_block(this.sfxGeneratorPtr);
+    const sfx = new Float32Array(this.sfx_wasm.memory.buffer, sfx_ptr, 256);
 
+    const music_gain = this.muted ? 0.0 : this.music_volume;
+    const sfx_gain = this.muted ? 0.0 : this.sfx_volume;
     const output = outputs[0];
     for (let i = 0; i < 128; i++) {
-      output[0][i] = bytes[i * 2];
-      output[1][i] = bytes[i * 2 + 1];
+      output[0][i] = music[i * 2] * music_gain + sfx[i * 2] * sfx_gain;
+      output[1][i] = music[i * 2 + 1] * music_gain + sfx[i * 2 + 1] * sfx_gain;
+    }
+
+    if (this.debug) {
+      this.debug.block(output, this.music_paused, this.port);
     }
 
-    const adl_playing = this.wasm.is_adl_playing(this.generatorPtr);
+    const adl_playing = this.sfx_wasm.is_adl_playing(this.sfxGeneratorPtr);
     if (this.adl_playing && !adl_playing) {
       this.adl_playing = false;
       //notify on "falling flank" to the main thread
@@ -0,0 +1,79 @@
+// The version is passed by the player when registering (sw.js?version=x.y.z),
+// a new version registers a new worker which precaches into a fresh cache
+// and removes the caches of older versions.
+const VERSION = new URL(self.location).searchParams.get("version") || "dev";
+const CACHE_PREFIX = "iron-wolf-webplayer-";
+const CACHE_NAME = CACHE_PREFIX + VERSION;
+
+const PRECACHE_URLS = [
+  "./",
+  "index.html",
+  "iron-wolf-webplayer.js",
+  "iron-wolf-webplayer_bg.wasm",
+  "oplProcessor.js",
+  "manifest.webmanifest",
+  "favicon.ico",
+  "splash1.png",
+  "splash2.png",
+  "splash3.png",
+  "shareware/AUDIOHED.WL1",
+  "shareware/AUDIOT.WL1",
+  "shareware/CONFIG.WL1",
+  "shareware/GAMEMAPS.WL1",
+  "shareware/MAPHEAD.WL1",
+  "shareware/VGADICT.WL1",
+  "shareware/VGAGRAPH.WL1",
+  "shareware/VGAHEAD.WL1",
+  "shareware/VSWAP.WL1",
+];
+
+// files that never change within a version are served from the cache first,
+// everything else goes to the network first so updates are picked up
+const CACHE_FIRST = [/\/shareware\//, /\.png$/, /\.ico$/];
+
+self.addEventListener("install", (event) => {
+  event.waitUntil(
+    caches
+      .open(CACHE_NAME)
+      .then((cache) => cache.addAll(PRECACHE_URLS))
+      .then(() => self.skipWaiting()),
+  );
+});
+
+self.addEventListener("activate", (event) => {
+  event.waitUntil(
+    caches
+      .keys()
+      .then((names) =>
+        Promise.all(
+          names
+            .filter((name) => name.startsWith(CACHE_PREFIX) && name !== CACHE_NAME)
+            .map((name) => caches.delete(name)),
+        ),
+      )
+      .then(() => self.clients.claim()),
+  );
+});
+
+self.addEventListener("fetch", (event) => {
+  const request = event.request;
+  if (request.method !== "GET" || new URL(request.url).origin !== self.location.origin) {
+    return;
+  }
+
+  if (CACHE_FIRST.some((pattern) => pattern.test(request.url))) {
+    event.respondWith(caches.match(request).then((cached) => cached || fetchAndCache(request)));
+  } else {
+    event.respondWith(fetchAndCache(request).catch(() => caches.match(request)));
+  }
+});
+
+function fetchAndCache(request) {
+  return fetch(request).then((response) => {
+    if (response.ok) {
+      const copy = response.clone();
+      caches.open(CACHE_NAME).then((cache) => cache.put(request, copy));
+    }
+    return response;
+  });
+}
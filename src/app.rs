@@ -1,144 +1,290 @@
+use std::cell::RefCell;
+use std::io::{Cursor, Read};
+use std::rc::Rc;
+#[cfg(feature = "web")]
+use std::time::Duration;
+
 use eframe::egui;
 use egui::FontDefinitions;
 use egui::{Color32, Frame, Pos2, Rect, RichText, Stroke};
-use iw::config::default_iw_config;
-use iw::loader::Loader;
-use iw::start::iw_start;
+#[cfg(feature = "web")]
 use iw::web::load_missing_shareware_data;
-use js_sys::{Reflect, Uint8Array};
+#[cfg(any(feature = "web", feature = "desktop"))]
+use iw::{
+    assets::WolfVariant, config::default_iw_config, def::IWConfig, loader::Loader, start::iw_start,
+};
 use poll_promise::Promise;
-use wasm_bindgen::prelude::*;
-use wasm_bindgen_futures::JsFuture;
-use wasm_bindgen_futures::spawn_local;
-use web_sys::{HtmlElement, KeyboardEvent, window};
-
-const KEYDOWN_EVENT: &str = "keydown";
-const KEYUP_EVENT: &str = "keyup";
-const CONTROL_KEY: &str = "Control";
-const KEYUP_DELAY_MS: i32 = 15;
+
+#[cfg(feature = "web")]
+use crate::audio::{self, AudioOutput, AudioSettings, AudioState};
+use crate::config_file::{
+    BUTTON_ACTIONS, BUTTONS, ConfigFile, DIGI_MODES, DIRECTIONS, MOUSE_ADJUSTMENT_RANGE,
+    MUSIC_MODES, SOUND_MODES, VIEW_SIZE_RANGE, bindable_scan_codes, scan_code_name,
+};
+use crate::engine_config::{EngineSettings, UNSUPPORTED_OPTIONS};
+#[cfg(feature = "web")]
+use crate::gamepad::{GAMEPAD_BUTTONS, GamepadSettings, GamepadState};
+#[cfg(feature = "web")]
+use crate::jukebox::{GameAudio, Jukebox, parse_audio};
+use crate::key_bindings::KeyBindings;
+#[cfg(feature = "web")]
+use crate::key_bindings::{
+    GAME_KEYS, KeyBinding, KeyPreset, MODIFIER_KEYS, MOUSE_INPUTS, MOUSE_MIDDLE, MOUSE_PRIMARY,
+    MOUSE_SECONDARY, key_label,
+};
+#[cfg(feature = "web")]
+use crate::keyboard::{dispatch_key_event, register_modifier_handler, start_playing, stop_playing};
+#[cfg(feature = "web")]
+use crate::mouse_look::{MouseLook, MouseLookSettings, default_button_key};
+#[cfg(all(feature = "web", feature = "debug"))]
+use crate::opl_debug::OplDebug;
+use crate::release::{Fingerprint, fingerprint};
+use crate::savegame::{
+    SaveGame, download_file, download_savegames_zip, normalise_savegame_name, savegame_slot,
+};
+use crate::settings::Settings;
+#[cfg(feature = "desktop")]
+use crate::shareware::shareware_files;
+#[cfg(feature = "desktop")]
+use crate::storage::FileStorage;
+use crate::storage::{PlatformStorage, Storage};
+use crate::task::{spawn_local, spawn_promise};
+#[cfg(feature = "web")]
+use crate::touch::{TouchControls, TouchMode, TouchSettings};
+use crate::validate::{
+    validate_audiohed, validate_gamemaps, validate_maphead, validate_vgadict, validate_vgahead,
+    validate_vswap,
+};
+use crate::version::GameVersion;
+#[cfg(feature = "web")]
+use crate::wav_export::{ExportAudio, export_wav};
+
 const PLAYER_DB_NAME: &str = "iron-wolf-player";
 const PLAYER_STORE: &str = "files";
 const IW_DB_NAME: &str = "iron-wolf";
 const IW_FILE_STORE: &str = "files";
-
-// files
-const AUDIOHED_PREFIX: &str = "AUDIOHED.WL";
-const AUDIOT_PREFIX: &str = "AUDIOT.WL";
-const CONFIG_PREFIX: &str = "CONFIG.WL";
-const GAMEMAPS_PREFIX: &str = "GAMEMAPS.WL";
-const MAPHEAD_PREFIX: &str = "MAPHEAD.WL";
-const VGADICT_PREFIX: &str = "VGADICT.WL";
-const VGAGRAPH_PREFIX: &str = "VGAGRAPH.WL";
-const VGAHEAD_PREFIX: &str = "VGAHEAD.WL";
-const VSWAP_PREFIX: &str = "VSWAP.WL";
-
-// files None means use the shareware version
+// key of the name of the selected installation in the PLAYER store,
+// an empty name selects the shareware
+const SELECTED_INSTALLATION_KEY: &str = "selected_installation";
+// key of the player settings (as json) in the PLAYER store
+const SETTINGS_KEY: &str = "settings";
+const SHAREWARE_NAME: &str = "Shareware (built-in)";
+
+// files, the extension is given by the GameVersion
+const AUDIOHED_PREFIX: &str = "AUDIOHED.";
+const AUDIOT_PREFIX: &str = "AUDIOT.";
+const CONFIG_PREFIX: &str = "CONFIG.";
+const GAMEMAPS_PREFIX: &str = "GAMEMAPS.";
+const MAPHEAD_PREFIX: &str = "MAPHEAD.";
+const VGADICT_PREFIX: &str = "VGADICT.";
+const VGAGRAPH_PREFIX: &str = "VGAGRAPH.";
+const VGAHEAD_PREFIX: &str = "VGAHEAD.";
+const VSWAP_PREFIX: &str = "VSWAP.";
+
+const DATA_FILE_PREFIXES: [&str; 9] = [
+    AUDIOHED_PREFIX,
+    AUDIOT_PREFIX,
+    CONFIG_PREFIX,
+    GAMEMAPS_PREFIX,
+    MAPHEAD_PREFIX,
+    VGADICT_PREFIX,
+    VGAGRAPH_PREFIX,
+    VGAHEAD_PREFIX,
+    VSWAP_PREFIX,
+];
+const ZIP_EXTENSION: &str = ".ZIP";
+// the largest data file (VSWAP.SOD) is below 2 MiB, bigger zip entries are rejected
+// before they are inflated
+const MAX_ZIP_ENTRY_SIZE: u64 = 8 * 1024 * 1024;
+
+// All uploaded installations, selected None means use the shareware version
 pub struct UploadState {
-    files: Option<UploadStateFiles>,
+    installations: Vec<UploadStateFiles>,
+    selected: Option<usize>,
 }
 
 impl UploadState {
     pub fn no_upload() -> UploadState {
-        UploadState { files: None }
+        UploadState {
+            installations: Vec::new(),
+            selected: None,
+        }
     }
 
-    fn with_files(files: UploadStateFiles) -> UploadState {
-        UploadState { files: Some(files) }
+    /// The files of the selected installation.
+    fn files(&self) -> Option<&UploadStateFiles> {
+        self.selected.and_then(|ix| self.installations.get(ix))
     }
 
-    fn is_complete(&self) -> bool {
-        self.files.as_ref().is_some_and(|f| f.is_complete())
+    fn files_mut(&mut self) -> Option<&mut UploadStateFiles> {
+        self.selected.and_then(|ix| self.installations.get_mut(ix))
     }
 
-    fn version(&self) -> usize {
-        self.files.as_ref().map_or_else(|| 0, |f| f.version)
+    fn selected_name(&self) -> String {
+        self.files().map_or_else(String::new, |f| f.name.clone())
     }
 
-    fn create_loader(&self) -> Loader {
-        if let Some(files) = &self.files {
-            let variant = match files.version {
-                1 => &iw::assets::W3D1,
-                3 => &iw::assets::W3D3,
-                6 => &iw::assets::W3D6,
-                _ => panic!("unknow version: {}", files.version),
-            };
-            let mut loader = if files.version == 1 {
-                // Take shareware files as default on override
-                // with file if supplied/found in upload
-                Loader::new_shareware()
+    /// Returns the installation uploaded files of the version go into:
+    /// the selected one if it has the same version and no new name is
+    /// given, a new one otherwise. The returned installation is selected.
+    fn installation_for_upload(
+        &mut self,
+        version: GameVersion,
+        new_name: &str,
+    ) -> Option<&mut UploadStateFiles> {
+        let new_name = installation_name(new_name);
+        if !new_name.is_empty() || self.files().is_none_or(|f| f.version != version) {
+            let base = if new_name.is_empty() {
+                version.extension().to_string()
             } else {
-                Loader::new_empty(variant)
+                new_name
             };
+            let name = self.unique_name(&base);
+            self.installations
+                .push(UploadStateFiles::new_empty(name, version));
+            self.selected = Some(self.installations.len() - 1);
+        }
+        self.files_mut()
+    }
 
-            if let Some(data) = &files.vgadict {
-                let file_name = file_name(VGADICT_PREFIX, files.version);
-                loader.load(file_name, data.to_vec());
-            }
+    /// The name, or the name with a number appended if an installation has it
+    /// already. The name is the key prefix of the installation's files.
+    fn unique_name(&self, name: &str) -> String {
+        let mut unique = name.to_string();
+        let mut n = 2;
+        while self.installations.iter().any(|i| i.name == unique) {
+            unique = format!("{} {}", name, n);
+            n += 1;
+        }
+        unique
+    }
 
-            if let Some(data) = &files.vgahead {
-                let file_name = file_name(VGAHEAD_PREFIX, files.version);
-                loader.load(file_name, data.to_vec());
-            }
+    /// Removes the selected installation and falls back to the shareware.
+    /// Returns the name of the removed installation.
+    fn remove_selected(&mut self) -> Option<String> {
+        let ix = self.selected.take()?;
+        Some(self.installations.remove(ix).name)
+    }
 
-            if let Some(data) = &files.vgagraph {
-                let file_name = file_name(VGAGRAPH_PREFIX, files.version);
-                loader.load(file_name, data.to_vec());
-            }
+    fn is_complete(&self) -> bool {
+        self.files().is_some_and(|f| f.is_complete())
+    }
 
-            if let Some(data) = &files.maphead {
-                let file_name = file_name(MAPHEAD_PREFIX, files.version);
-                loader.load(file_name, data.to_vec());
-            }
+    fn is_valid(&self) -> bool {
+        self.files().is_none_or(|f| f.errors.is_empty())
+    }
 
-            if let Some(data) = &files.gamemaps {
-                let file_name = file_name(GAMEMAPS_PREFIX, files.version);
-                loader.load(file_name, data.to_vec());
-            }
+    fn release(&self) -> Option<&Fingerprint> {
+        self.files().map(|f| &f.release)
+    }
 
-            if let Some(data) = &files.vswap {
-                let file_name = file_name(VSWAP_PREFIX, files.version);
-                loader.load(file_name, data.to_vec());
-            }
+    fn version(&self) -> Option<GameVersion> {
+        self.files().map(|f| f.version)
+    }
 
-            if let Some(data) = &files.config {
-                let file_name = file_name(CONFIG_PREFIX, files.version);
-                loader.load(file_name, data.to_vec());
-            }
+    /// The engine's description of the selected game, the shareware if
+    /// nothing is uploaded.
+    #[cfg(any(feature = "web", feature = "desktop"))]
+    fn variant(&self) -> Result<&'static WolfVariant, String> {
+        let version = self.version().unwrap_or(GameVersion::WL1);
+        version
+            .variant()
+            .ok_or_else(|| format!("{} is not supported by the engine", version.title()))
+    }
 
-            if let Some(data) = &files.audiohed {
-                let file_name = file_name(AUDIOHED_PREFIX, files.version);
-                loader.load(file_name, data.to_vec());
-            }
+    /// The uploaded files of the selected installation, by the name the
+    /// engine looks for.
+    #[cfg(any(feature = "web", feature = "desktop"))]
+    fn data_files(&self) -> Result<Vec<(String, &[u8])>, String> {
+        let Some(files) = self.files() else {
+            return Ok(Vec::new());
+        };
+        let missing = files.missing_files();
+        if files.version != GameVersion::WL1 && !missing.is_empty() {
+            return Err(format!("missing files: {}", missing.join(", ")));
+        }
 
-            if let Some(data) = &files.audiot {
-                let file_name = file_name(AUDIOT_PREFIX, files.version);
-                loader.load(file_name, data.to_vec());
-            }
+        let uploaded = [
+            (VGADICT_PREFIX, &files.vgadict),
+            (VGAHEAD_PREFIX, &files.vgahead),
+            (VGAGRAPH_PREFIX, &files.vgagraph),
+            (MAPHEAD_PREFIX, &files.maphead),
+            (GAMEMAPS_PREFIX, &files.gamemaps),
+            (VSWAP_PREFIX, &files.vswap),
+            (CONFIG_PREFIX, &files.config),
+            (AUDIOHED_PREFIX, &files.audiohed),
+            (AUDIOT_PREFIX, &files.audiot),
+        ];
+        Ok(uploaded
+            .into_iter()
+            .filter_map(|(prefix, data)| Some((file_name(prefix, files.version), data.as_deref()?)))
+            .collect())
+    }
 
-            loader
-        } else {
+    #[cfg(feature = "web")]
+    fn create_loader(&self) -> Result<Loader, String> {
+        let variant = self.variant()?;
+        let mut loader = if self.version().is_none_or(|v| v == GameVersion::WL1) {
+            // Take shareware files as default on override
+            // with file if supplied/found in upload
             Loader::new_shareware()
+        } else {
+            Loader::new_empty(variant)
+        };
+        for (file_name, data) in self.data_files()? {
+            loader.load(file_name, data.to_vec());
+        }
+        Ok(loader)
+    }
+
+    /// The desktop engine reads the game from disk. The files of the
+    /// installation (on top of the compiled in shareware for the shareware)
+    /// are copied to the engine's directory, where it keeps its save games.
+    #[cfg(feature = "desktop")]
+    fn create_loader(&self, storage: &FileStorage) -> Result<Loader, String> {
+        let variant = self.variant()?;
+        let write = |file_name: &str, data: &[u8]| {
+            pollster::block_on(storage.put_file(file_name, data, IW_DB_NAME, IW_FILE_STORE))
+        };
+        if self.version().is_none_or(|v| v == GameVersion::WL1) {
+            for (file_name, data) in shareware_files() {
+                write(&file_name, data)?;
+            }
+        }
+        // the uploaded files replace the shareware ones
+        for (file_name, data) in self.data_files()? {
+            write(&file_name, data)?;
         }
+        Ok(Loader {
+            variant,
+            data_path: storage.store_path(IW_DB_NAME, IW_FILE_STORE),
+            patch_path: None,
+        })
     }
 }
 
 struct UploadStateFiles {
-    version: usize, // 3 or 6
-    audiohed: Option<Uint8Array>,
-    gamemaps: Option<Uint8Array>,
-    audiot: Option<Uint8Array>,
-    config: Option<Uint8Array>,
-    maphead: Option<Uint8Array>,
-    vgadict: Option<Uint8Array>,
-    vgagraph: Option<Uint8Array>,
-    vgahead: Option<Uint8Array>,
-    vswap: Option<Uint8Array>,
+    name: String,
+    version: GameVersion,
+    audiohed: Option<Vec<u8>>,
+    gamemaps: Option<Vec<u8>>,
+    audiot: Option<Vec<u8>>,
+    config: Option<Vec<u8>>,
+    maphead: Option<Vec<u8>>,
+    vgadict: Option<Vec<u8>>,
+    vgagraph: Option<Vec<u8>>,
+    vgahead: Option<Vec<u8>>,
+    vswap: Option<Vec<u8>>,
+    // validation errors per file prefix
+    errors: Vec<(&'static str, String)>,
+    release: Fingerprint,
 }
 
 impl UploadStateFiles {
-    pub fn new_empty(version: usize) -> UploadStateFiles {
+    pub fn new_empty(name: String, version: GameVersion) -> UploadStateFiles {
         UploadStateFiles {
-            version: version,
+            name,
+            version,
             audiohed: None,
             gamemaps: None,
             audiot: None,
@@ -148,32 +294,175 @@ impl UploadStateFiles {
             vgagraph: None,
             vgahead: None,
             vswap: None,
+            errors: Vec::new(),
+            release: Fingerprint::Unknown,
+        }
+    }
+
+    /// Files the engine cannot start without. The CONFIG is not
+    /// one of them, the engine writes a default one.
+    fn missing_files(&self) -> Vec<String> {
+        DATA_FILE_PREFIXES
+            .iter()
+            .filter(|prefix| **prefix != CONFIG_PREFIX && self.get(prefix).is_none())
+            .map(|prefix| file_name(prefix, self.version))
+            .collect()
+    }
+
+    fn get(&self, prefix: &str) -> Option<&Vec<u8>> {
+        match prefix {
+            AUDIOHED_PREFIX => self.audiohed.as_ref(),
+            AUDIOT_PREFIX => self.audiot.as_ref(),
+            CONFIG_PREFIX => self.config.as_ref(),
+            GAMEMAPS_PREFIX => self.gamemaps.as_ref(),
+            MAPHEAD_PREFIX => self.maphead.as_ref(),
+            VGADICT_PREFIX => self.vgadict.as_ref(),
+            VGAGRAPH_PREFIX => self.vgagraph.as_ref(),
+            VGAHEAD_PREFIX => self.vgahead.as_ref(),
+            VSWAP_PREFIX => self.vswap.as_ref(),
+            _ => None,
+        }
+    }
+
+    fn error(&self, prefix: &str) -> Option<&str> {
+        self.errors
+            .iter()
+            .find(|(p, _)| *p == prefix)
+            .map(|(_, e)| e.as_str())
+    }
+
+    /// Checks the headers of all present files and how they reference
+    /// each other. The result ends up in `errors`.
+    fn validate(&mut self) {
+        let audiot = self.audiot.as_deref();
+        let gamemaps = self.gamemaps.as_deref();
+        let vgagraph = self.vgagraph.as_deref();
+        let layout = self.version.layout();
+        let layout = layout.as_ref();
+
+        let mut errors = Vec::new();
+        let mut check = |prefix: &'static str, result: Result<(), String>| {
+            if let Err(e) = result {
+                errors.push((prefix, e));
+            }
+        };
+        if let Some(data) = &self.audiohed {
+            check(AUDIOHED_PREFIX, validate_audiohed(data, audiot, layout));
         }
+        if let Some(data) = gamemaps {
+            check(GAMEMAPS_PREFIX, validate_gamemaps(data));
+        }
+        if let Some(data) = &self.maphead {
+            check(MAPHEAD_PREFIX, validate_maphead(data, gamemaps));
+        }
+        if let Some(data) = &self.vgadict {
+            check(VGADICT_PREFIX, validate_vgadict(data));
+        }
+        if let Some(data) = &self.vgahead {
+            check(VGAHEAD_PREFIX, validate_vgahead(data, vgagraph, layout));
+        }
+        if let Some(data) = &self.vswap {
+            check(VSWAP_PREFIX, validate_vswap(data, layout));
+        }
+        self.errors = errors;
+    }
+
+    fn identify_release(&mut self) {
+        let files: Vec<(String, Vec<u8>)> = DATA_FILE_PREFIXES
+            .iter()
+            .filter(|prefix| **prefix != CONFIG_PREFIX)
+            .filter_map(|prefix| {
+                self.get(prefix)
+                    .map(|data| (file_name(prefix, self.version), data.to_vec()))
+            })
+            .collect();
+        self.release = fingerprint(&files);
     }
 
-    pub fn reset(&mut self, version: usize) {
-        self.audiohed = None;
-        self.gamemaps = None;
-        self.audiot = None;
-        self.config = None;
-        self.maphead = None;
-        self.vgadict = None;
-        self.vgagraph = None;
-        self.vgahead = None;
-        self.vswap = None;
-        self.version = version;
+    fn set(&mut self, prefix: &str, data: Vec<u8>) {
+        match prefix {
+            AUDIOHED_PREFIX => self.audiohed = Some(data),
+            AUDIOT_PREFIX => self.audiot = Some(data),
+            CONFIG_PREFIX => self.config = Some(data),
+            GAMEMAPS_PREFIX => self.gamemaps = Some(data),
+            MAPHEAD_PREFIX => self.maphead = Some(data),
+            VGADICT_PREFIX => self.vgadict = Some(data),
+            VGAGRAPH_PREFIX => self.vgagraph = Some(data),
+            VGAHEAD_PREFIX => self.vgahead = Some(data),
+            VSWAP_PREFIX => self.vswap = Some(data),
+            _ => panic!("unknown file prefix: {}", prefix),
+        }
     }
 
+    /// Whether the engine can start with the files alone, see
+    /// missing_files.
     pub fn is_complete(&self) -> bool {
-        self.audiohed.is_some()
-            && self.gamemaps.is_some()
-            && self.audiot.is_some()
-            && self.config.is_some()
-            && self.maphead.is_some()
-            && self.vgadict.is_some()
-            && self.vgagraph.is_some()
-            && self.vgahead.is_some()
-            && self.vswap.is_some()
+        self.missing_files().is_empty()
+    }
+}
+
+#[derive(Clone, Copy)]
+enum StartStage {
+    #[cfg(any(feature = "web", feature = "desktop"))]
+    Config,
+    #[cfg(feature = "web")]
+    SharewareFetch,
+    #[cfg(any(feature = "web", feature = "desktop"))]
+    Loader,
+    EngineStart,
+}
+
+impl StartStage {
+    fn description(&self) -> &'static str {
+        match self {
+            #[cfg(any(feature = "web", feature = "desktop"))]
+            StartStage::Config => "creating the engine config",
+            #[cfg(feature = "web")]
+            StartStage::SharewareFetch => "fetching the shareware data",
+            #[cfg(any(feature = "web", feature = "desktop"))]
+            StartStage::Loader => "preparing the game data",
+            StartStage::EngineStart => "starting the engine",
+        }
+    }
+}
+
+struct StartError {
+    stage: StartStage,
+    message: String,
+}
+
+impl StartError {
+    fn new(stage: StartStage, message: impl Into<String>) -> StartError {
+        StartError {
+            stage,
+            message: message.into(),
+        }
+    }
+}
+
+/// The game the player window was closed for and how the last one ended.
+/// The desktop engine only runs on the main thread, main.rs runs it
+/// between two player windows.
+#[cfg(feature = "desktop")]
+#[derive(Default)]
+pub struct NativeLaunch {
+    game: Option<(Loader, IWConfig)>,
+    error: Option<StartError>,
+}
+
+#[cfg(feature = "desktop")]
+impl NativeLaunch {
+    /// Runs the game the player asked for until its window is closed.
+    /// Returns false if the player was closed without starting a game.
+    pub fn run_game(&mut self) -> bool {
+        let Some((loader, iw_config)) = self.game.take() else {
+            return false;
+        };
+        if let Err(e) = iw_start(loader, iw_config) {
+            log::error!("starting the engine failed: {}", e);
+            self.error = Some(StartError::new(StartStage::EngineStart, e));
+        }
+        true
     }
 }
 
@@ -182,23 +471,104 @@ pub struct FileUpload {
     pub bytes: Vec<u8>,
 }
 
+enum UploadOutcome {
+    Accepted,
+    Renamed(String),
+    Ignored(String),
+    // read, but storing it failed, it is only there until the page is left
+    NotStored(String),
+}
+
+struct UploadReportEntry {
+    name: String,
+    outcome: UploadOutcome,
+}
+
+impl UploadReportEntry {
+    fn ignored(name: String, reason: impl Into<String>) -> UploadReportEntry {
+        UploadReportEntry {
+            name,
+            outcome: UploadOutcome::Ignored(reason.into()),
+        }
+    }
+}
+
 pub struct IWApp {
     is_expanded: bool,
     playing: bool,
 
-    file_upload_promise: Option<Promise<Vec<FileUpload>>>,
-    upload: UploadState,
-
-    confirm_reset: Option<Rect>,
+    storage: PlatformStorage,
 
-    //settings
-    show_frame_rate: bool,
+    file_upload_promise: Option<Promise<(Vec<FileUpload>, Vec<UploadReportEntry>)>>,
+    // (uploaded name, error) of the files that could not be stored
+    file_store_promise: Option<Promise<Vec<(String, String)>>>,
+    // name of the installation the next upload creates, empty to upload
+    // into the selected one
+    new_installation_name: String,
+    upload: UploadState,
+    upload_report: Vec<UploadReportEntry>,
+
+    confirm_delete: Option<Rect>,
+    delete_promise: Option<Promise<Result<(), String>>>,
+    delete_error: Option<String>,
+
+    start_promise: Option<Promise<Result<(), StartError>>>,
+    start_error: Option<StartError>,
+    #[cfg(feature = "desktop")]
+    launch: Rc<RefCell<NativeLaunch>>,
+
+    savegame_promise: Option<Promise<Vec<SaveGame>>>,
+    savegame_upload_promise: Option<Promise<Result<String, String>>>,
+    savegames: Vec<SaveGame>,
+    savegame_status: Option<String>,
+
+    settings: Settings,
+    // the key bindings of the settings, shared with the modifier handler
+    key_bindings: Rc<RefCell<KeyBindings>>,
+    #[cfg(feature = "web")]
+    gamepad: GamepadState,
+    // the browser engine gets its input and volumes through the player
+    #[cfg(feature = "web")]
+    touch_controls: TouchControls,
+    #[cfg(feature = "web")]
+    mouse_look: MouseLook,
+    #[cfg(feature = "web")]
+    audio_output: AudioOutput,
+    #[cfg(all(feature = "web", feature = "debug"))]
+    opl_debug: OplDebug,
+
+    // the CONFIG file of the selected installation (or the shareware)
+    config_file_promise: Option<Promise<Option<Vec<u8>>>>,
+    config_file: Option<ConfigFile>,
+    // edited since it was last stored
+    config_file_changed: bool,
+    config_file_error: Option<String>,
+
+    // the music of the installation the jukebox was loaded for, it plays
+    // through the browser engine's worklet
+    #[cfg(feature = "web")]
+    jukebox: Jukebox,
+    #[cfg(feature = "web")]
+    music_source: Option<String>,
+    #[cfg(feature = "web")]
+    music_promise: Option<Promise<Result<GameAudio, String>>>,
+    #[cfg(feature = "web")]
+    music_error: Option<String>,
+    #[cfg(feature = "web")]
+    export_promise: Option<Promise<Result<String, String>>>,
+    #[cfg(feature = "web")]
+    export_status: Option<String>,
 }
 
 impl eframe::App for IWApp {
     fn ui(&mut self, ui: &mut egui::Ui, _frame: &mut eframe::Frame) {
-        self.handle_file_upload();
-        self.forward_key_events(ui);
+        self.handle_start();
+        self.handle_file_upload(ui.ctx());
+        self.handle_installation_delete();
+        self.handle_savegames(ui.ctx());
+        self.handle_config_file();
+        #[cfg(feature = "web")]
+        self.handle_music(ui.ctx());
 
         let animation_speed = 0.25;
         let t = ui.animate_bool_with_time(
@@ -210,6 +580,21 @@ impl eframe::App for IWApp {
         let max_width = 280.0;
         let current_width = MENUE_MIN_WDITH + (max_width - MENUE_MIN_WDITH) * t;
 
+        // on the desktop the engine has its own window and input
+        #[cfg(feature = "web")]
+        {
+            self.handle_mute_shortcut(ui);
+            self.audio_output.apply(&self.settings.audio);
+            #[cfg(feature = "debug")]
+            self.opl_debug.update();
+            self.forward_key_events(ui, current_width);
+            self.forward_gamepad(ui.ctx());
+            let mut game_area = ui.max_rect();
+            game_area.set_right(game_area.right() - current_width);
+            self.forward_touch_controls(ui, game_area);
+            self.forward_mouse_look(ui, game_area);
+        }
+
         egui::Panel::right("wolf_sidebar")
             .resizable(false)
             .exact_size(current_width)
@@ -238,8 +623,14 @@ impl eframe::App for IWApp {
 
                 ui.add_space(20.0);
 
+                #[cfg(feature = "web")]
+                if self.playing {
+                    self.render_audio_status(ui, t);
+                }
                 self.render_savegame_download(ui, t);
                 self.render_file_upload(ui, t);
+                #[cfg(feature = "web")]
+                self.render_jukebox(ui);
                 self.render_settings(ui);
 
                 let rect = ui.clip_rect();
@@ -279,53 +670,29 @@ impl eframe::App for IWApp {
                                     .color(ICON_COLOUR),
                             );
 
-                            if play_response.clicked() {
-                                self.playing = true;
-                                let window = window().expect("No window object found");
-                                let document = window.document().expect("No document object found");
-
-                                Reflect::set(
-                                    &window,
-                                    &JsValue::from_str("iw_playing"),
-                                    &true.into(),
-                                )
-                                .expect("mark iw as playing");
-
-                                let element = document
-                                    .get_element_by_id("vga")
-                                    .expect("Element not found");
-                                if let Some(html_element) = element.dyn_ref::<HtmlElement>() {
-                                    html_element.focus().expect("Failed to focus element");
-                                }
-
-                                let mut loader = self.upload.create_loader();
-                                let need_load_shareware = self.upload.version() == 0
-                                    || (self.upload.version() == 1 && !self.upload.is_complete());
-                                let show_frame_rate = self.show_frame_rate;
-                                spawn_local(async move {
-                                    let mut iw_config =
-                                        default_iw_config().expect("default config");
-                                    iw_config.options.show_frame_rate = show_frame_rate;
-                                    if need_load_shareware {
-                                        load_missing_shareware_data(&mut loader)
-                                            .await
-                                            .expect("load shareware data");
-                                    }
-
-                                    iw_start(loader, iw_config).expect("iw start");
-                                });
+                            if play_response.clicked() && self.upload.is_valid() {
+                                self.start_game(ui.ctx());
                             }
 
                             if play_response.hovered() {
                                 ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
                             }
 
-                            if self.upload.is_complete() {
+                            if !self.upload.is_valid() {
+                                ui.label(
+                                    RichText::new("(Invalid upload, see sidebar)")
+                                        .size(10.0)
+                                        .color(ICON_COLOUR),
+                                );
+                            } else if self.upload.is_complete() {
                                 ui.label(
                                     RichText::new("(Uploaded Version)")
                                         .size(10.0)
                                         .color(ICON_COLOUR),
                                 );
+                                if let Some(release) = self.upload.release() {
+                                    release_label(ui, release);
+                                }
                             } else {
                                 ui.label(
                                     RichText::new("(Shareware Version)")
@@ -338,26 +705,67 @@ impl eframe::App for IWApp {
                 });
             });
 
-        if let Some(pos) = self.confirm_reset {
+        #[cfg(all(feature = "web", feature = "debug"))]
+        self.opl_debug.show(ui);
+
+        if let Some(error) = &self.start_error {
+            let mut retry = false;
+            let mut close = false;
+            egui::Window::new("Could not start the game")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+                .show(ui, |ui| {
+                    ui.label(format!("Failed while: {}", error.stage.description()));
+                    ui.label(RichText::new(&error.message).monospace());
+
+                    ui.horizontal(|ui| {
+                        retry = ui.button("Retry").clicked();
+                        close = ui.button("Close").clicked();
+                    });
+                });
+            if retry || close {
+                self.start_error = None;
+            }
+            if retry {
+                self.start_game(ui.ctx());
+            }
+        }
+
+        if let Some(pos) = self.confirm_delete {
             let dialog_pos = egui::pos2(pos.left(), pos.bottom() + 50.0);
-            egui::Window::new("Confirm reset")
+            egui::Window::new("Confirm delete")
                 .collapsible(false)
                 .resizable(false)
                 .pivot(egui::Align2::CENTER_CENTER)
                 .fixed_pos(dialog_pos)
                 .show(ui, |ui| {
-                    ui.label("Delete all uploaded data and reset to shareware?");
+                    ui.label(format!(
+                        "Delete the installation '{}' and all its uploaded data?",
+                        self.upload.selected_name()
+                    ));
 
                     ui.horizontal(|ui| {
                         if ui.button("Yes").clicked() {
-                            spawn_local(async {
-                                reset_files_indexeddb().await.expect("file reset");
-                            });
-                            self.confirm_reset = None;
-                            self.upload.files = None;
+                            if let Some(name) = self.upload.remove_selected() {
+                                let storage = self.storage.clone();
+                                let egui_ctx = ui.ctx().clone();
+                                self.delete_error = None;
+                                self.delete_promise = Some(spawn_promise(async move {
+                                    let result = delete_installation(&storage, &name)
+                                        .await
+                                        .map_err(|e| format!("Deleting '{}' failed: {}", name, e));
+                                    egui_ctx.request_repaint(); // Wake ui thread
+                                    result
+                                }));
+                                store_selected_installation(&self.storage, String::new());
+                            }
+                            self.confirm_delete = None;
+                            self.upload_report.clear();
+                            self.reload_config_file(ui.ctx());
                         }
                         if ui.button("No").clicked() {
-                            self.confirm_reset = None;
+                            self.confirm_delete = None;
                         }
                     });
                 });
@@ -366,86 +774,394 @@ impl eframe::App for IWApp {
 }
 
 impl IWApp {
-    pub fn new(cc: &eframe::CreationContext<'_>, upload_state: UploadState) -> IWApp {
+    pub fn new(
+        cc: &eframe::CreationContext<'_>,
+        storage: PlatformStorage,
+        upload_state: UploadState,
+        settings: Settings,
+        #[cfg(feature = "desktop")] launch: Rc<RefCell<NativeLaunch>>,
+    ) -> IWApp {
         let mut fonts = FontDefinitions::default();
         egui_phosphor::add_to_fonts(&mut fonts, egui_phosphor::Variant::Regular);
         cc.egui_ctx.set_fonts(fonts);
 
-        register_ctrl_handler();
+        let key_bindings = Rc::new(RefCell::new(settings.key_bindings.clone()));
+        #[cfg(feature = "web")]
+        {
+            register_modifier_handler(key_bindings.clone());
+            audio::register_resume_handler();
+        }
+
+        let config_version = upload_state.version().unwrap_or(GameVersion::WL1);
+        let config_file_promise = Some(load_config_file_promise(
+            &cc.egui_ctx,
+            &storage,
+            config_version,
+        ));
+        let savegame_promise = Some(load_savegames_promise(&cc.egui_ctx, &storage));
+        // the desktop engine ran between two player windows
+        #[cfg(not(feature = "desktop"))]
+        let start_error = None;
+        #[cfg(feature = "desktop")]
+        let start_error = launch.borrow_mut().error.take();
 
         IWApp {
             is_expanded: false,
             playing: false,
 
+            storage,
+
             file_upload_promise: None,
+            file_store_promise: None,
+            new_installation_name: String::new(),
             upload: upload_state,
+            upload_report: Vec::new(),
+
+            confirm_delete: None,
+            delete_promise: None,
+            delete_error: None,
+
+            start_promise: None,
+            start_error,
+            #[cfg(feature = "desktop")]
+            launch,
+
+            savegame_promise,
+            savegame_upload_promise: None,
+            savegames: Vec::new(),
+            savegame_status: None,
+
+            settings,
+            key_bindings,
+            #[cfg(feature = "web")]
+            gamepad: GamepadState::default(),
+            #[cfg(feature = "web")]
+            touch_controls: TouchControls::default(),
+            #[cfg(feature = "web")]
+            mouse_look: MouseLook::install(),
+            #[cfg(feature = "web")]
+            audio_output: AudioOutput::default(),
+            #[cfg(all(feature = "web", feature = "debug"))]
+            opl_debug: OplDebug::install(),
+
+            config_file_promise,
+            config_file: None,
+            config_file_changed: false,
+            config_file_error: None,
+
+            #[cfg(feature = "web")]
+            jukebox: Jukebox::default(),
+            #[cfg(feature = "web")]
+            music_source: None,
+            #[cfg(feature = "web")]
+            music_promise: None,
+            #[cfg(feature = "web")]
+            music_error: None,
+            #[cfg(feature = "web")]
+            export_promise: None,
+            #[cfg(feature = "web")]
+            export_status: None,
+        }
+    }
+
+    #[cfg(feature = "web")]
+    fn start_game(&mut self, ctx: &egui::Context) {
+        let loader = match self.upload.create_loader() {
+            Ok(loader) => loader,
+            Err(message) => {
+                self.start_error = Some(StartError::new(StartStage::Loader, message));
+                return;
+            }
+        };
+
+        self.playing = true;
+        start_playing();
+        // a context of an earlier start, the engine creates a new one later
+        audio::resume();
+
+        let need_load_shareware = self.upload.version().is_none()
+            || (self.upload.version() == Some(GameVersion::WL1) && !self.upload.is_complete());
+        let show_frame_rate = self.settings.show_frame_rate;
+        let engine_settings = self.settings.engine_config.clone();
+        let egui_ctx = ctx.clone();
+        self.start_promise = Some(Promise::spawn_local(async move {
+            let result = start_iw(
+                loader,
+                need_load_shareware,
+                show_frame_rate,
+                engine_settings,
+            )
+            .await;
+            egui_ctx.request_repaint(); // Wake ui thread
+            result
+        }));
+    }
+
+    /// The engine needs the main thread, the player window closes for it
+    /// (see main.rs).
+    #[cfg(feature = "desktop")]
+    fn start_game(&mut self, ctx: &egui::Context) {
+        let loader = match self.upload.create_loader(&self.storage) {
+            Ok(loader) => loader,
+            Err(message) => {
+                self.start_error = Some(StartError::new(StartStage::Loader, message));
+                return;
+            }
+        };
+        let iw_config =
+            match engine_config(self.settings.show_frame_rate, &self.settings.engine_config) {
+                Ok(iw_config) => iw_config,
+                Err(error) => {
+                    self.start_error = Some(error);
+                    return;
+                }
+            };
 
-            confirm_reset: None,
+        self.launch.borrow_mut().game = Some((loader, iw_config));
+        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+    }
+
+    #[cfg(not(any(feature = "web", feature = "desktop")))]
+    fn start_game(&mut self, _ctx: &egui::Context) {
+        self.start_error = Some(StartError::new(
+            StartStage::EngineStart,
+            "the player was built without the engine, build it with the desktop feature",
+        ));
+    }
 
-            show_frame_rate: false,
+    fn handle_start(&mut self) {
+        let Some(start_promise) = self.start_promise.take() else {
+            return;
+        };
+        match start_promise.try_take() {
+            Ok(Ok(())) => {}
+            Ok(Err(error)) => {
+                log::error!("{}: {}", error.stage.description(), error.message);
+                self.playing = false;
+                #[cfg(feature = "web")]
+                stop_playing();
+                self.start_error = Some(error);
+            }
+            Err(start_promise) => self.start_promise = Some(start_promise),
         }
     }
 
-    fn handle_file_upload(&mut self) {
-        if let Some(upload_promise) = &self.file_upload_promise {
-            if let Some(file_uploads) = upload_promise.ready() {
-                if file_uploads.is_empty() {
-                    return;
+    fn handle_file_upload(&mut self, ctx: &egui::Context) {
+        self.handle_file_store();
+        let Some(upload_promise) = self.file_upload_promise.take() else {
+            return;
+        };
+        let (file_uploads, mut report) = match upload_promise.try_take() {
+            Ok(result) => result,
+            Err(upload_promise) => {
+                self.file_upload_promise = Some(upload_promise);
+                return;
+            }
+        };
+
+        let version = detect_upload_version(&file_uploads);
+        if let Some(unsupported) = version.filter(|v| !v.is_supported()) {
+            for file_upload in file_uploads {
+                report.push(UploadReportEntry::ignored(
+                    file_upload.name,
+                    format!("{} is not supported by the engine", unsupported.title()),
+                ));
+            }
+            self.upload_report = report;
+            return;
+        }
+        #[cfg(feature = "web")]
+        {
+            self.music_source = None; // the audio files may have changed
+        }
+        if let Some(version) = version {
+            if let Some(installation) = self
+                .upload
+                .installation_for_upload(version, &self.new_installation_name)
+            {
+                store_selected_installation(&self.storage, installation.name.clone());
+            }
+            self.new_installation_name.clear();
+        }
+
+        let mut store_jobs = Vec::new();
+        for file_upload in file_uploads {
+            let Some((prefix, file_version)) = normalise_data_file_name(&file_upload.name) else {
+                report.push(UploadReportEntry::ignored(
+                    file_upload.name,
+                    "not a game data file",
+                ));
+                continue;
+            };
+            let (Some(version), Some(files)) = (version, self.upload.files_mut()) else {
+                continue; // cannot happen, a data file always yields a version
+            };
+            if file_version != version {
+                report.push(UploadReportEntry::ignored(
+                    file_upload.name,
+                    format!(
+                        "belongs to {}, the upload is {}",
+                        file_version.extension(),
+                        version.extension()
+                    ),
+                ));
+                continue;
+            }
+
+            let file_name_db = file_name(prefix, version);
+            // the store job needs its own copy, the upload keeps the original
+            store_jobs.push((
+                file_upload.name.clone(),
+                installation_key(&files.name, &file_name_db),
+                file_upload.bytes.clone(),
+            ));
+            files.set(prefix, file_upload.bytes);
+
+            let outcome = if file_upload.name == file_name_db {
+                UploadOutcome::Accepted
+            } else {
+                UploadOutcome::Renamed(file_name_db)
+            };
+            report.push(UploadReportEntry {
+                name: file_upload.name,
+                outcome,
+            });
+        }
+
+        if let Some(files) = self.upload.files_mut() {
+            files.validate();
+            files.identify_release();
+        }
+        self.upload_report = report;
+
+        let storage = self.storage.clone();
+        let egui_ctx = ctx.clone();
+        self.file_store_promise = Some(spawn_promise(async move {
+            let mut failures = Vec::new();
+            for (upload_name, name_db, data_db) in store_jobs {
+                if let Err(e) = store_file(&storage, &name_db, &data_db).await {
+                    log::error!("storing {} failed: {}", name_db, e);
+                    failures.push((upload_name, e));
                 }
+            }
+            egui_ctx.request_repaint(); // Wake ui thread
+            failures
+        }));
+        self.reload_config_file(ctx);
+    }
 
-                let version = if file_uploads[0].name.ends_with("WL6") {
-                    6
-                } else if file_uploads[0].name.ends_with("WL3") {
-                    3
-                } else {
-                    return;
-                };
+    /// Shows why the files of a deleted installation are still stored.
+    /// The installation is gone from the list anyway, it comes back with
+    /// the next page load.
+    fn handle_installation_delete(&mut self) {
+        let Some(promise) = self.delete_promise.take() else {
+            return;
+        };
+        match promise.try_take() {
+            Ok(result) => {
+                if let Err(e) = result {
+                    log::error!("{}", e);
+                    self.delete_error = Some(e);
+                }
+            }
+            Err(promise) => self.delete_promise = Some(promise),
+        }
+    }
 
-                if let Some(files) = &mut self.upload.files {
-                    if files.version != version {
-                        files.reset(version);
+    /// Marks the files of the last upload that could not be stored in the report.
+    fn handle_file_store(&mut self) {
+        let Some(promise) = self.file_store_promise.take() else {
+            return;
+        };
+        match promise.try_take() {
+            Ok(failures) => {
+                for (upload_name, e) in failures {
+                    if let Some(entry) = self
+                        .upload_report
+                        .iter_mut()
+                        .find(|entry| entry.name == upload_name)
+                    {
+                        entry.outcome = UploadOutcome::NotStored(e);
                     }
-                } else {
-                    self.upload.files = Some(UploadStateFiles::new_empty(version));
                 }
+            }
+            Err(promise) => self.file_store_promise = Some(promise),
+        }
+    }
 
-                for file_upload in file_uploads {
-                    let data = Uint8Array::from(file_upload.bytes.as_slice());
-
-                    if file_upload.name == file_name(GAMEMAPS_PREFIX, version) {
-                        self.upload.files.as_mut().unwrap().gamemaps = Some(data);
-                    } else if file_upload.name == file_name(AUDIOHED_PREFIX, version) {
-                        self.upload.files.as_mut().unwrap().audiohed = Some(data);
-                    } else if file_upload.name == file_name(AUDIOT_PREFIX, version) {
-                        self.upload.files.as_mut().unwrap().audiot = Some(data);
-                    } else if file_upload.name == file_name(CONFIG_PREFIX, version) {
-                        self.upload.files.as_mut().unwrap().config = Some(data);
-                    } else if file_upload.name == file_name(MAPHEAD_PREFIX, version) {
-                        self.upload.files.as_mut().unwrap().maphead = Some(data);
-                    } else if file_upload.name == file_name(VGADICT_PREFIX, version) {
-                        self.upload.files.as_mut().unwrap().vgadict = Some(data);
-                    } else if file_upload.name == file_name(VGAGRAPH_PREFIX, version) {
-                        self.upload.files.as_mut().unwrap().vgagraph = Some(data);
-                    } else if file_upload.name == file_name(VGAHEAD_PREFIX, version) {
-                        self.upload.files.as_mut().unwrap().vgahead = Some(data);
-                    } else if file_upload.name == file_name(VSWAP_PREFIX, version) {
-                        self.upload.files.as_mut().unwrap().vswap = Some(data);
-                    } else {
-                        continue; // ignore all other files
-                    }
+    fn handle_savegames(&mut self, ctx: &egui::Context) {
+        if let Some(promise) = self.savegame_promise.take() {
+            match promise.try_take() {
+                Ok(savegames) => self.savegames = savegames,
+                Err(promise) => self.savegame_promise = Some(promise),
+            }
+        }
 
-                    let file_name_db = file_upload.name.clone(); // clone for async move
-                    let data_db = Uint8Array::from(file_upload.bytes.as_slice());
-                    spawn_local(async move {
-                        store_file_indexeddb(&file_name_db, data_db)
-                            .await
-                            .expect("file store");
-                    });
+        if let Some(promise) = &self.savegame_upload_promise
+            && let Some(result) = promise.ready()
+        {
+            match result {
+                Ok(file_name) => {
+                    self.savegame_status = Some(format!("{} uploaded", file_name));
+                    self.savegame_promise = Some(load_savegames_promise(ctx, &self.storage));
+                }
+                Err(msg) => {
+                    self.savegame_status = Some(msg.clone());
                 }
+            }
+            self.savegame_upload_promise = None;
+        }
+    }
 
-                self.file_upload_promise = None;
+    #[cfg(feature = "web")]
+    /// Points out sound that waits for a click or could not be set up.
+    fn render_audio_status(&mut self, ui: &mut egui::Ui, t: f32) {
+        let (icon, text) = match audio::audio_state() {
+            AudioState::Suspended => {
+                // the state changes without any egui event
+                ui.ctx().request_repaint_after(Duration::from_millis(500));
+                (
+                    egui_phosphor::regular::SPEAKER_SLASH,
+                    "Click to enable sound".to_string(),
+                )
             }
+            AudioState::Failed(error) => (
+                egui_phosphor::regular::WARNING,
+                format!("No sound: {}", error),
+            ),
+            AudioState::NotStarted => {
+                ui.ctx().request_repaint_after(Duration::from_millis(500));
+                return;
+            }
+            AudioState::Running => return,
+        };
+
+        let response = ui
+            .horizontal(|ui| {
+                ui.add_space(5.0);
+                let response = ui.label(RichText::new(icon).size(24.0).color(ICON_COLOUR));
+                if t > 0.1 {
+                    let opacity = (t - 0.1) / 0.9;
+                    ui.add_space(10.0);
+                    ui.add(
+                        egui::Label::new(
+                            RichText::new(&text)
+                                .color(egui::Color32::WHITE.linear_multiply(opacity)),
+                        )
+                        .wrap(),
+                    );
+                }
+                response
+            })
+            .inner
+            .on_hover_text(&text);
+        if response.clicked() {
+            audio::resume();
+        }
+        if response.hovered() {
+            ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
         }
+        ui.add_space(10.0);
     }
 
     fn render_savegame_download(&mut self, ui: &mut egui::Ui, t: f32) {
@@ -462,14 +1178,88 @@ impl IWApp {
                 if !self.is_expanded {
                     self.is_expanded = true;
                 }
+                // the engine may have written new save games in the meantime
+                self.savegame_promise = Some(load_savegames_promise(ui.ctx(), &self.storage));
             };
 
             if t > 0.1 {
-                ui.scope(|_ui| {
-                    // TODO render save-game files for download
+                ui.scope(|ui| {
+                    let opacity = (t - 0.1) / 0.9;
+                    ui.set_clip_rect(ui.available_rect_before_wrap());
+                    ui.add_space(10.0);
+
+                    let text = RichText::new("ZIP")
+                        .strong()
+                        .color(egui::Color32::WHITE.linear_multiply(opacity));
+                    if ui
+                        .add_enabled(!self.savegames.is_empty(), egui::Button::new(text))
+                        .clicked()
+                        && let Err(e) = download_savegames_zip(&self.savegames)
+                    {
+                        self.savegame_status = Some(format!("zip download failed: {}", e));
+                    }
+
+                    let text = RichText::new("UPLOAD")
+                        .strong()
+                        .color(egui::Color32::WHITE.linear_multiply(opacity));
+                    if ui.button(text).clicked() {
+                        let egui_ctx = ui.ctx().clone();
+                        let storage = self.storage.clone();
+                        self.savegame_status = None;
+                        self.savegame_upload_promise = Some(spawn_promise(async move {
+                            let result = upload_savegame(&storage).await;
+                            egui_ctx.request_repaint(); // Wake ui thread
+                            result
+                        }));
+                    }
                 });
             }
         });
+
+        if self.is_expanded {
+            let mut download_error = None;
+            if self.savegames.is_empty() {
+                ui.horizontal(|ui| {
+                    ui.add_space(25.0);
+                    ui.label(RichText::new("No save games found").color(ICON_COLOUR));
+                });
+            }
+            for savegame in &self.savegames {
+                ui.horizontal(|ui| {
+                    ui.add_space(25.0);
+                    let download = ui.add(
+                        egui::Button::new(
+                            RichText::new(egui_phosphor::regular::DOWNLOAD_SIMPLE)
+                                .size(16.0)
+                                .color(ICON_COLOUR),
+                        )
+                        .frame(false),
+                    );
+                    if download.clicked()
+                        && let Err(e) = download_file(&savegame.file_name, &savegame.data)
+                    {
+                        download_error =
+                            Some(format!("download of {} failed: {}", savegame.file_name, e));
+                    }
+                    ui.label(
+                        RichText::new(format!("{}: {}", savegame.slot, savegame.description))
+                            .color(ICON_COLOUR),
+                    )
+                    .on_hover_text(&savegame.file_name);
+                });
+            }
+            if download_error.is_some() {
+                self.savegame_status = download_error;
+            }
+
+            if let Some(status) = &self.savegame_status {
+                ui.horizontal(|ui| {
+                    ui.add_space(25.0);
+                    ui.label(RichText::new(status).size(10.0).color(ICON_COLOUR));
+                });
+            }
+        }
+
         ui.add_space(15.0);
     }
 
@@ -483,10 +1273,9 @@ impl IWApp {
                         .color(ICON_COLOUR),
                 )
                 .clicked()
+                && !self.is_expanded
             {
-                if !self.is_expanded {
-                    self.is_expanded = true;
-                }
+                self.is_expanded = true;
             };
 
             if t > 0.1 {
@@ -499,88 +1288,116 @@ impl IWApp {
                         .strong()
                         .color(egui::Color32::WHITE.linear_multiply(opacity));
 
-                    if ui.button(text).clicked() {
+                    if ui
+                        .button(text)
+                        .on_hover_text("Select the game data files or a zip archive of them")
+                        .clicked()
+                    {
                         let egui_ctx = ui.ctx().clone();
-                        self.file_upload_promise =
-                            Some(poll_promise::Promise::spawn_local(async move {
-                                let file_uploads = open_files().await;
-                                egui_ctx.request_repaint(); // Wake ui thread
-                                file_uploads
-                            }));
+                        self.file_upload_promise = Some(spawn_promise(async move {
+                            let file_uploads = open_files().await;
+                            egui_ctx.request_repaint(); // Wake ui thread
+                            file_uploads
+                        }));
                     }
 
-                    let reset_button = ui.button("RESET");
-                    if reset_button.clicked() {
-                        self.confirm_reset = Some(reset_button.rect);
+                    let delete_button =
+                        ui.add_enabled(self.upload.files().is_some(), egui::Button::new("DELETE"));
+                    if delete_button.clicked() {
+                        self.confirm_delete = Some(delete_button.rect);
                     };
                 });
             }
         });
 
         if self.is_expanded {
-            if let Some(files) = &self.upload.files {
-                file_upload_status(
-                    ui,
-                    &file_name(AUDIOHED_PREFIX, files.version),
-                    files.audiohed.is_some(),
-                );
-                file_upload_status(
-                    ui,
-                    &file_name(AUDIOT_PREFIX, files.version),
-                    files.audiot.is_some(),
-                );
-                file_upload_status(
-                    ui,
-                    &file_name(CONFIG_PREFIX, files.version),
-                    files.config.is_some(),
-                );
-                file_upload_status(
-                    ui,
-                    &file_name(GAMEMAPS_PREFIX, files.version),
-                    files.gamemaps.is_some(),
-                );
-                file_upload_status(
-                    ui,
-                    &file_name(MAPHEAD_PREFIX, files.version),
-                    files.maphead.is_some(),
-                );
-                file_upload_status(
-                    ui,
-                    &file_name(VGADICT_PREFIX, files.version),
-                    files.vgadict.is_some(),
-                );
-                file_upload_status(
-                    ui,
-                    &file_name(VGAGRAPH_PREFIX, files.version),
-                    files.vgagraph.is_some(),
-                );
-                file_upload_status(
-                    ui,
-                    &file_name(VGAHEAD_PREFIX, files.version),
-                    files.vgahead.is_some(),
-                );
-                file_upload_status(
-                    ui,
-                    &file_name(VSWAP_PREFIX, files.version),
-                    files.vswap.is_some(),
-                );
+            self.render_installation_select(ui);
+
+            if let Some(files) = self.upload.files() {
+                game_title(ui, files.version);
+                for prefix in DATA_FILE_PREFIXES {
+                    file_upload_status(
+                        ui,
+                        &file_name(prefix, files.version),
+                        files.get(prefix).is_some(),
+                        files.error(prefix),
+                    );
+                }
             } else {
                 // shareware is always available
-                file_upload_status(ui, &file_name(AUDIOHED_PREFIX, 1), true);
-                file_upload_status(ui, &file_name(AUDIOT_PREFIX, 1), true);
-                file_upload_status(ui, &file_name(CONFIG_PREFIX, 1), true);
-                file_upload_status(ui, &file_name(GAMEMAPS_PREFIX, 1), true);
-                file_upload_status(ui, &file_name(MAPHEAD_PREFIX, 1), true);
-                file_upload_status(ui, &file_name(VGADICT_PREFIX, 1), true);
-                file_upload_status(ui, &file_name(VGAGRAPH_PREFIX, 1), true);
-                file_upload_status(ui, &file_name(VGAHEAD_PREFIX, 1), true);
-                file_upload_status(ui, &file_name(VSWAP_PREFIX, 1), true);
+                game_title(ui, GameVersion::WL1);
+                for prefix in DATA_FILE_PREFIXES {
+                    file_upload_status(ui, &file_name(prefix, GameVersion::WL1), true, None);
+                }
             };
+
+            if !self.upload_report.is_empty() {
+                ui.add_space(5.0);
+                for entry in &self.upload_report {
+                    upload_report_entry(ui, entry);
+                }
+            }
+            if let Some(error) = &self.delete_error {
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    ui.add_space(25.0);
+                    ui.label(
+                        RichText::new(egui_phosphor::regular::WARNING)
+                            .size(10.0)
+                            .color(ICON_COLOUR),
+                    );
+                    ui.label(RichText::new(error).size(10.0).color(ICON_COLOUR));
+                });
+            }
         }
 
         ui.add_space(15.0);
     }
 
+    fn render_installation_select(&mut self, ui: &mut egui::Ui) {
+        let mut selected = self.upload.selected;
+        ui.horizontal(|ui| {
+            ui.add_space(25.0);
+            let selected_text = self
+                .upload
+                .files()
+                .map_or(SHAREWARE_NAME, |f| f.name.as_str());
+            egui::ComboBox::from_id_salt("installation_select")
+                .selected_text(selected_text)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut selected, None, SHAREWARE_NAME);
+                    for (ix, installation) in self.upload.installations.iter().enumerate() {
+                        ui.selectable_value(
+                            &mut selected,
+                            Some(ix),
+                            format!("{} - {}", installation.name, installation.version.title()),
+                        );
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            ui.add_space(25.0);
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_installation_name)
+                    .hint_text("New installation")
+                    .desired_width(120.0),
+            )
+            .on_hover_text(
+                "Give the next upload a name to keep it as a new installation \
+                 next to the selected one",
+            );
+        });
+
+        if selected != self.upload.selected {
+            self.upload.selected = selected;
+            self.upload_report.clear();
+            self.delete_error = None;
+            store_selected_installation(&self.storage, self.upload.selected_name());
+            self.reload_config_file(ui.ctx());
+        }
+    }
+
     fn render_settings(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
             ui.add_space(5.0);
@@ -595,6 +1412,8 @@ impl IWApp {
                 if !self.is_expanded {
                     self.is_expanded = true;
                 }
+                // the game may have changed its config in the meantime
+                self.reload_config_file(ui.ctx());
             };
             if self.is_expanded {
                 ui.label(RichText::new("SETTINGS").size(16.0).color(ICON_COLOUR));
@@ -602,218 +1421,883 @@ impl IWApp {
         });
 
         if self.is_expanded {
+            let before = self.settings.clone();
+
             ui.horizontal(|ui| {
                 ui.add_space(25.0);
                 ui.checkbox(
-                    &mut self.show_frame_rate,
+                    &mut self.settings.show_frame_rate,
                     RichText::new("Show framerate").color(ICON_COLOUR),
                 );
             });
-        }
 
-        ui.add_space(15.0);
-    }
+            // the desktop engine reads its input itself
+            #[cfg(feature = "web")]
+            {
+                ui.horizontal(|ui| {
+                    ui.add_space(25.0);
+                    ui.label(
+                        RichText::new(egui_phosphor::regular::GAME_CONTROLLER)
+                            .size(16.0)
+                            .color(ICON_COLOUR),
+                    );
+                    let text = self
+                        .gamepad
+                        .connected
+                        .as_deref()
+                        .unwrap_or("No controller connected");
+                    ui.label(RichText::new(text).size(10.0).color(ICON_COLOUR));
+                });
+            }
 
-    fn forward_key_events(&self, ui: &egui::Ui) {
-        if let Some(window) = window() {
-            if let Some(document) = window.document() {
-                let input = ui.input(|i| i.clone());
-                for event in &input.events {
-                    if let egui::Event::Key { key, pressed, .. } = event {
-                        let init = web_sys::KeyboardEventInit::new();
-                        init.set_key(egui_key_to_event_key(key));
-                        init.set_bubbles(true);
-                        init.set_cancelable(true);
+            egui::CollapsingHeader::new(RichText::new("Engine options").color(ICON_COLOUR))
+                .id_salt("engine_options")
+                .show(ui, |ui| {
+                    engine_settings_widget(ui, &mut self.settings.engine_config);
+                });
+
+            #[cfg(feature = "web")]
+            {
+                egui::CollapsingHeader::new(RichText::new("Audio").color(ICON_COLOUR))
+                    .id_salt("audio")
+                    .show(ui, |ui| {
+                        audio_settings_widget(ui, &mut self.settings.audio);
+                        #[cfg(feature = "debug")]
+                        ui.horizontal(|ui| {
+                            ui.add_space(25.0);
+                            ui.checkbox(
+                                &mut self.opl_debug.open,
+                                RichText::new("OPL debug view").color(ICON_COLOUR),
+                            );
+                        });
+                    });
 
-                        let event_type = if *pressed { KEYDOWN_EVENT } else { KEYUP_EVENT };
-                        let event =
-                            KeyboardEvent::new_with_keyboard_event_init_dict(event_type, &init)
-                                .expect("event");
+                egui::CollapsingHeader::new(RichText::new("Gamepad").color(ICON_COLOUR))
+                    .id_salt("gamepad")
+                    .show(ui, |ui| {
+                        gamepad_settings_widget(ui, &mut self.settings.gamepad);
+                    });
 
-                        let vga = document
-                            .get_element_by_id("vga")
-                            .expect("Element not found");
+                egui::CollapsingHeader::new(RichText::new("Touch controls").color(ICON_COLOUR))
+                    .id_salt("touch_controls")
+                    .show(ui, |ui| {
+                        touch_settings_widget(ui, &mut self.settings.touch);
+                    });
 
-                        vga.dispatch_event(&event).expect("event dispatch");
-                    }
+                egui::CollapsingHeader::new(RichText::new("Mouse look").color(ICON_COLOUR))
+                    .id_salt("mouse_look")
+                    .show(ui, |ui| {
+                        mouse_look_settings_widget(ui, &mut self.settings.mouse_look);
+                    });
+                if !self.settings.mouse_look.enabled {
+                    MouseLook::unlock();
                 }
+
+                egui::CollapsingHeader::new(RichText::new("Key bindings").color(ICON_COLOUR))
+                    .id_salt("key_bindings")
+                    .show(ui, |ui| {
+                        key_bindings_widget(ui, &mut self.settings.key_bindings);
+                    });
             }
+
+            if self.settings != before {
+                *self.key_bindings.borrow_mut() = self.settings.key_bindings.clone();
+                store_settings(&self.storage, &self.settings);
+            }
+
+            self.render_config_file(ui);
         }
+
+        ui.add_space(15.0);
     }
-}
 
-pub async fn open_files() -> Vec<FileUpload> {
-    let files = rfd::AsyncFileDialog::new().pick_files().await.unwrap();
+    fn render_config_file(&mut self, ui: &mut egui::Ui) {
+        let version = self.config_version();
+        let title = format!("Game config ({})", file_name(CONFIG_PREFIX, version));
+        egui::CollapsingHeader::new(RichText::new(title).color(ICON_COLOUR))
+            .id_salt("config_file")
+            .show(ui, |ui| {
+                if let Some(error) = &self.config_file_error {
+                    ui.label(RichText::new(error).size(10.0).color(ICON_COLOUR));
+                }
+                let Some(config_file) = &mut self.config_file else {
+                    if self.config_file_error.is_none() {
+                        ui.label(
+                            RichText::new("No config yet, the game writes one when it starts")
+                                .size(10.0)
+                                .color(ICON_COLOUR),
+                        );
+                    }
+                    return;
+                };
 
-    let mut result = Vec::with_capacity(files.len());
-    for file in files {
-        let bytes = file.read().await;
-        result.push(FileUpload {
-            name: file.file_name(),
-            bytes,
-        })
+                let before = config_file.clone();
+                // the running game overwrites the file with its own state
+                let editing = ui
+                    .add_enabled_ui(!self.playing, |ui| config_file_widgets(ui, config_file))
+                    .inner;
+                if self.playing {
+                    ui.label(
+                        RichText::new("Changes are possible once the game is closed")
+                            .size(10.0)
+                            .color(ICON_COLOUR),
+                    );
+                }
+                if *config_file != before {
+                    self.config_file_changed = true;
+                }
+                if self.config_file_changed && !editing {
+                    self.store_config_file();
+                }
+            });
     }
-    result
-}
 
-pub async fn load_upload_state() -> UploadState {
-    let mut state = UploadStateFiles::new_empty(0);
-    let mut v = 0;
-    let (audiohed, v_l) = load_file(AUDIOHED_PREFIX, false).await;
-    if v_l >= v {
-        v = v_l;
-        state.audiohed = audiohed;
+    fn config_version(&self) -> GameVersion {
+        self.upload.version().unwrap_or(GameVersion::WL1)
     }
-    let (gamemaps, v_l) = load_file(GAMEMAPS_PREFIX, false).await;
-    if v_l >= v {
-        v = v_l;
-        state.gamemaps = gamemaps;
+
+    fn reload_config_file(&mut self, ctx: &egui::Context) {
+        self.config_file_promise = Some(load_config_file_promise(
+            ctx,
+            &self.storage,
+            self.config_version(),
+        ));
     }
-    let (audiot, v_l) = load_file(AUDIOT_PREFIX, false).await;
-    if v_l >= v {
-        v = v_l;
-        state.audiot = audiot;
+
+    #[cfg(feature = "web")]
+    fn handle_music(&mut self, ctx: &egui::Context) {
+        if let Some(promise) = self.music_promise.take() {
+            match promise.try_take() {
+                Ok(Ok(audio)) => {
+                    self.jukebox = Jukebox::new(audio);
+                    self.music_error = None;
+                }
+                Ok(Err(e)) => {
+                    self.jukebox = Jukebox::default();
+                    self.music_error = Some(e);
+                }
+                Err(promise) => self.music_promise = Some(promise),
+            }
+        }
+
+        if let Some(promise) = self.export_promise.take() {
+            match promise.try_take() {
+                Ok(Ok(status)) | Ok(Err(status)) => self.export_status = Some(status),
+                Err(promise) => self.export_promise = Some(promise),
+            }
+        }
+
+        if self.jukebox.current.is_some() {
+            self.jukebox.update(ctx.input(|i| i.time));
+            ctx.request_repaint_after(Duration::from_millis(250)); // keep the position moving
+        }
     }
-    let (config, v_l) = load_file(CONFIG_PREFIX, true).await;
-    if v_l >= v {
-        v = v_l;
-        state.config = config;
+
+    #[cfg(feature = "web")]
+    /// Loads the music of the selected installation, if the jukebox does
+    /// not have it already.
+    fn load_music(&mut self, ctx: &egui::Context) {
+        let source = self.upload.selected_name();
+        if self.music_source.as_ref() == Some(&source) {
+            return;
+        }
+        self.jukebox.stop();
+        self.music_source = Some(source);
+
+        let audio = self
+            .upload
+            .files()
+            .and_then(|f| Some((f.audiohed.clone()?, f.audiot.clone()?)));
+        let shareware = self.upload.version().is_none_or(|v| v == GameVersion::WL1);
+        let egui_ctx = ctx.clone();
+        self.music_promise = Some(spawn_promise(async move {
+            let (audiohed, audiot) = match audio {
+                Some(audio) => audio,
+                None if shareware => load_shareware_audio().await?,
+                None => return Err("AUDIOHED or AUDIOT not uploaded".to_string()),
+            };
+            let audio = parse_audio(&audiohed, &audiot);
+            egui_ctx.request_repaint(); // Wake ui thread
+            audio
+        }));
     }
-    let (maphead, v_l) = load_file(MAPHEAD_PREFIX, false).await;
-    if v_l >= v {
-        v = v_l;
-        state.maphead = maphead;
+
+    #[cfg(feature = "web")]
+    fn render_jukebox(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.add_space(5.0);
+            if ui
+                .label(
+                    RichText::new(egui_phosphor::regular::MUSIC_NOTES)
+                        .size(24.0)
+                        .color(ICON_COLOUR),
+                )
+                .clicked()
+                && !self.is_expanded
+            {
+                self.is_expanded = true;
+            };
+            if self.is_expanded {
+                ui.label(RichText::new("JUKEBOX").size(16.0).color(ICON_COLOUR));
+            }
+        });
+
+        if self.is_expanded {
+            self.load_music(ui.ctx());
+
+            let available = audio::is_available();
+            if !available {
+                ui.horizontal(|ui| {
+                    ui.add_space(25.0);
+                    ui.label(
+                        RichText::new("Plays through the sound of the game, start it first")
+                            .size(10.0)
+                            .color(ICON_COLOUR),
+                    );
+                });
+            }
+            if let Some(error) = &self.music_error {
+                ui.horizontal(|ui| {
+                    ui.add_space(25.0);
+                    ui.label(RichText::new(error).size(10.0).color(ICON_COLOUR));
+                });
+            }
+
+            let mut export = None;
+            ui.add_enabled_ui(available && !self.jukebox.tracks.is_empty(), |ui| {
+                jukebox_controls(ui, &mut self.jukebox);
+                egui::CollapsingHeader::new(RichText::new("Tracks").color(ICON_COLOUR))
+                    .id_salt("jukebox_tracks")
+                    .show(ui, |ui| {
+                        let mut play = None;
+                        for (ix, track) in self.jukebox.tracks.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                ui.add_space(25.0);
+                                if export_button(ui, self.export_promise.is_none()) {
+                                    let seconds =
+                                        track.duration() * self.jukebox.export_loops as f64;
+                                    export = Some((
+                                        format!("{}.wav", track.name),
+                                        ExportAudio::Music(track.imf.clone(), seconds),
+                                    ));
+                                }
+                                let selected = self.jukebox.current == Some(ix);
+                                let text = format!(
+                                    "{} ({})",
+                                    track.name,
+                                    format_play_time(track.duration())
+                                );
+                                if ui
+                                    .selectable_label(
+                                        selected,
+                                        RichText::new(text).color(ICON_COLOUR),
+                                    )
+                                    .on_hover_text(format!("chunk {}", track.chunk))
+                                    .clicked()
+                                {
+                                    play = Some(ix);
+                                }
+                            });
+                        }
+                        if let Some(ix) = play {
+                            self.jukebox.play(ix);
+                        }
+                    });
+
+                egui::CollapsingHeader::new(RichText::new("Sound effects").color(ICON_COLOUR))
+                    .id_salt("jukebox_sounds")
+                    .show(ui, |ui| {
+                        for sound in &self.jukebox.sounds {
+                            ui.horizontal(|ui| {
+                                ui.add_space(25.0);
+                                if export_button(ui, self.export_promise.is_none()) {
+                                    export = Some((
+                                        format!("{}.wav", sound.name),
+                                        ExportAudio::Sound(sound.adl.clone()),
+                                    ));
+                                }
+                                ui.label(RichText::new(&sound.name).color(ICON_COLOUR))
+                                    .on_hover_text(format!("chunk {}", sound.chunk));
+                            });
+                        }
+                    });
+
+                ui.horizontal(|ui| {
+                    ui.add_space(25.0);
+                    ui.label(RichText::new("Music passes per WAV").color(ICON_COLOUR));
+                    ui.add(egui::DragValue::new(&mut self.jukebox.export_loops).range(1..=10));
+                });
+            });
+
+            if let Some((file_name, audio)) = export {
+                let egui_ctx = ui.ctx().clone();
+                self.export_status = Some(format!("exporting {}", file_name));
+                self.export_promise = Some(spawn_promise(async move {
+                    let result = export_wav(file_name, audio).await;
+                    egui_ctx.request_repaint(); // Wake ui thread
+                    result
+                }));
+            }
+            if let Some(status) = &self.export_status {
+                ui.horizontal(|ui| {
+                    ui.add_space(25.0);
+                    ui.label(RichText::new(status).size(10.0).color(ICON_COLOUR));
+                });
+            }
+        }
+
+        ui.add_space(15.0);
     }
-    let (vgadict, v_l) = load_file(VGADICT_PREFIX, false).await;
-    if v_l >= v {
-        v = v_l;
-        state.vgadict = vgadict;
+
+    fn handle_config_file(&mut self) {
+        let Some(promise) = self.config_file_promise.take() else {
+            return;
+        };
+        let engine_copy = match promise.try_take() {
+            Ok(engine_copy) => engine_copy,
+            Err(promise) => {
+                self.config_file_promise = Some(promise);
+                return;
+            }
+        };
+
+        // the engine's copy is the newest one, the uploaded one the fallback
+        self.config_file_changed = false;
+        let data =
+            engine_copy.or_else(|| self.upload.files().and_then(|f| f.config.as_ref()).cloned());
+        (self.config_file, self.config_file_error) = match data.map(|d| ConfigFile::parse(&d)) {
+            Some(Ok(config_file)) => (Some(config_file), None),
+            Some(Err(e)) => (
+                None,
+                Some(format!(
+                    "{} {}",
+                    file_name(CONFIG_PREFIX, self.config_version()),
+                    e
+                )),
+            ),
+            None => (None, None),
+        };
     }
-    let (vgagraph, v_l) = load_file(VGAGRAPH_PREFIX, false).await;
-    if v_l >= v {
-        v = v_l;
-        state.vgagraph = vgagraph;
+
+    /// Writes the edited config to the installation and to the IW_DB,
+    /// so the game and the player see the same file.
+    fn store_config_file(&mut self) {
+        self.config_file_changed = false;
+        let Some(config_file) = &self.config_file else {
+            return;
+        };
+        let data = config_file.to_bytes();
+        let config_name = file_name(CONFIG_PREFIX, self.config_version());
+        let installation_key = self.upload.files_mut().map(|files| {
+            files.config = Some(data.clone());
+            installation_key(&files.name, &config_name)
+        });
+
+        let storage = self.storage.clone();
+        spawn_local(async move {
+            if let Err(e) = store_config(&storage, &config_name, &data).await {
+                log::warn!("storing {} failed: {}", config_name, e);
+            }
+            if let Some(key) = installation_key
+                && let Err(e) = store_file(&storage, &key, &data).await
+            {
+                log::warn!("storing {} failed: {}", key, e);
+            }
+        });
     }
-    let (vgahead, v_l) = load_file(VGAHEAD_PREFIX, false).await;
-    if v_l >= v {
-        v = v_l;
-        state.vgahead = vgahead;
+}
+
+// the input reaches the browser engine through the player, the desktop
+// engine reads its own
+#[cfg(feature = "web")]
+impl IWApp {
+    fn forward_key_events(&self, ui: &egui::Ui, sidebar_width: f32) {
+        let bindings = &self.settings.key_bindings;
+        // clicks in the sidebar are not meant for the game
+        let game_right = ui.max_rect().right() - sidebar_width;
+        let input = ui.input(|i| i.clone());
+        for event in &input.events {
+            let (key, pressed) = match event {
+                // the mute shortcut, see handle_mute_shortcut
+                egui::Event::Key { key, modifiers, .. }
+                    if self.playing && is_mute_shortcut(*key, *modifiers) =>
+                {
+                    continue;
+                }
+                egui::Event::Key { key, pressed, .. } => {
+                    (bindings.map(egui_key_to_event_key(key)), *pressed)
+                }
+                egui::Event::PointerButton {
+                    pos,
+                    button,
+                    pressed,
+                    ..
+                } if self.playing && pos.x < game_right && !self.settings.touch.is_active() => {
+                    if self.settings.mouse_look.enabled && !self.mouse_look.is_locked() {
+                        // the click capturing the mouse is not meant for the game
+                        if *pressed {
+                            MouseLook::lock();
+                        }
+                        continue;
+                    }
+                    let input = pointer_button_input(*button);
+                    let key = bindings.lookup(input).or_else(|| {
+                        default_button_key(input).filter(|_| self.mouse_look.is_locked())
+                    });
+                    let Some(key) = key else {
+                        continue;
+                    };
+                    (key, *pressed)
+                }
+                _ => continue,
+            };
+
+            dispatch_key_event(key, pressed);
+        }
     }
-    let (vswap, v_l) = load_file(VSWAP_PREFIX, false).await;
-    if v_l >= v {
-        v = v_l;
-        state.vswap = vswap;
+
+    fn handle_mute_shortcut(&mut self, ui: &egui::Ui) {
+        if !self.playing {
+            return;
+        }
+        let toggled = ui.input(|i| {
+            i.events.iter().any(|event| {
+                matches!(
+                    event,
+                    egui::Event::Key {
+                        key,
+                        pressed: true,
+                        repeat: false,
+                        modifiers,
+                        ..
+                    } if is_mute_shortcut(*key, *modifiers)
+                )
+            })
+        });
+        if toggled {
+            self.settings.audio.muted = !self.settings.audio.muted;
+            store_settings(&self.storage, &self.settings);
+        }
     }
 
-    if v == 0 {
-        UploadState::no_upload()
-    } else {
-        state.version = v;
-        UploadState::with_files(state)
+    /// Touches are emulated as mouse clicks too, these are not forwarded
+    /// while the touch controls are active (see forward_key_events).
+    fn forward_touch_controls(&mut self, ui: &egui::Ui, game_area: Rect) {
+        let changes = if self.playing && self.settings.touch.is_active() {
+            let events = ui.input(|i| i.events.clone());
+            let changes = self
+                .touch_controls
+                .update(&events, game_area, &self.settings.touch);
+            let painter = ui.ctx().layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("touch_controls"),
+            ));
+            self.touch_controls
+                .paint(&painter, game_area, &self.settings.touch, ICON_COLOUR);
+            changes
+        } else {
+            self.touch_controls.release()
+        };
+        for (key, pressed) in changes {
+            dispatch_key_event(&key, pressed);
+        }
     }
+
+    fn forward_mouse_look(&mut self, ui: &egui::Ui, game_area: Rect) {
+        let dt = ui.input(|i| i.unstable_dt);
+        let (changes, pending) = self.mouse_look.update(dt, &self.settings.mouse_look);
+        if pending {
+            ui.ctx().request_repaint();
+        }
+        for (key, pressed) in changes {
+            dispatch_key_event(&key, pressed);
+        }
+
+        if self.mouse_look.is_locked() {
+            let painter = ui.ctx().layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("mouse_look"),
+            ));
+            painter.text(
+                egui::pos2(game_area.center().x, game_area.top() + 10.0),
+                egui::Align2::CENTER_TOP,
+                "Mouse captured, press Esc to release it",
+                egui::FontId::proportional(12.0),
+                ICON_COLOUR,
+            );
+        }
+    }
+
+    fn forward_gamepad(&mut self, ctx: &egui::Context) {
+        let changes = self.gamepad.poll(&self.settings.gamepad);
+        if self.gamepad.connected.is_some() {
+            // the Gamepad API has no events for buttons and sticks
+            ctx.request_repaint();
+        }
+        for (key, pressed) in changes {
+            dispatch_key_event(&key, pressed);
+        }
+    }
+}
+
+async fn open_files() -> (Vec<FileUpload>, Vec<UploadReportEntry>) {
+    let mut result = Vec::new();
+    let mut report = Vec::new();
+
+    let Some(files) = rfd::AsyncFileDialog::new().pick_files().await else {
+        return (result, report);
+    };
+
+    for file in files {
+        let bytes = file.read().await;
+        let name = file.file_name();
+        if name.to_ascii_uppercase().ends_with(ZIP_EXTENSION) {
+            match unpack_zip(&bytes, &mut report) {
+                Ok(mut entries) => result.append(&mut entries),
+                Err(e) => report.push(UploadReportEntry::ignored(
+                    name,
+                    format!("could not unpack: {}", e),
+                )),
+            }
+        } else {
+            result.push(FileUpload { name, bytes })
+        }
+    }
+    (result, report)
+}
+
+/// Extracts all game data files from a zip archive. The entry names
+/// are kept as they are (including directories), they get normalised
+/// like every other uploaded file name. Entries larger than any data
+/// file are left out and reported.
+fn unpack_zip(
+    bytes: &[u8],
+    report: &mut Vec<UploadReportEntry>,
+) -> Result<Vec<FileUpload>, String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+
+    let mut result = Vec::new();
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| e.to_string())?;
+        if entry.is_dir() {
+            continue;
+        }
+
+        let name = entry.name().to_string();
+        if normalise_data_file_name(&name).is_none() {
+            continue; // readme, executables, ...
+        }
+
+        // the size in the header is not trusted, the read is capped as well
+        let mut bytes = Vec::new();
+        if entry.size() <= MAX_ZIP_ENTRY_SIZE {
+            (&mut entry)
+                .take(MAX_ZIP_ENTRY_SIZE + 1)
+                .read_to_end(&mut bytes)
+                .map_err(|e| e.to_string())?;
+        }
+        if entry.size() > MAX_ZIP_ENTRY_SIZE || bytes.len() as u64 > MAX_ZIP_ENTRY_SIZE {
+            report.push(UploadReportEntry::ignored(
+                name,
+                format!("larger than {} MiB", MAX_ZIP_ENTRY_SIZE / (1024 * 1024)),
+            ));
+            continue;
+        }
+        result.push(FileUpload { name, bytes });
+    }
+    Ok(result)
+}
+
+/// Maps an uploaded file name (e.g. `wolf3d/vswap.wl6`) to the prefix
+/// and version of the data file it is. Directories and the case of the
+/// name are ignored. Returns None if the name is not a known data file.
+fn normalise_data_file_name(name: &str) -> Option<(&'static str, GameVersion)> {
+    let base = name.rsplit(['/', '\\']).next()?.trim().to_ascii_uppercase();
+    DATA_FILE_PREFIXES.iter().find_map(|prefix| {
+        let version = GameVersion::from_extension(base.strip_prefix(prefix)?)?;
+        Some((*prefix, version))
+    })
+}
+
+fn detect_upload_version(file_uploads: &[FileUpload]) -> Option<GameVersion> {
+    let versions: Vec<GameVersion> = file_uploads
+        .iter()
+        .filter_map(|file_upload| normalise_data_file_name(&file_upload.name))
+        .map(|(_, version)| version)
+        .collect();
+    preferred_version(&versions)
+}
+
+/// The version of a set of files is the one most of the files agree on,
+/// on a tie the preferred version (see GameVersion::ALL) wins.
+fn preferred_version(versions: &[GameVersion]) -> Option<GameVersion> {
+    // least preferred first, max_by_key returns the last maximum
+    GameVersion::ALL
+        .into_iter()
+        .rev()
+        .map(|version| (version, versions.iter().filter(|v| **v == version).count()))
+        .filter(|(_, count)| *count > 0)
+        .max_by_key(|(_, count)| *count)
+        .map(|(version, _)| version)
+}
+
+fn load_savegames_promise(ctx: &egui::Context, storage: &impl Storage) -> Promise<Vec<SaveGame>> {
+    let egui_ctx = ctx.clone();
+    let storage = storage.clone();
+    spawn_promise(async move {
+        let savegames = load_savegames(&storage).await;
+        egui_ctx.request_repaint(); // Wake ui thread
+        savegames
+    })
 }
 
-async fn load_file(file_prefix: &str, check_iw: bool) -> (Option<Uint8Array>, usize) {
-    if check_iw {
-        let (data, version) = load_file_from_store(file_prefix, IW_DB_NAME, IW_FILE_STORE).await;
-        if version != 0 {
-            return (data, version);
+/// Lists all save games the engine stored in the IW_DB.
+async fn load_savegames(storage: &impl Storage) -> Vec<SaveGame> {
+    let file_names = match storage.list_files(IW_DB_NAME, IW_FILE_STORE).await {
+        Ok(file_names) => file_names,
+        Err(e) => {
+            log::warn!("listing save games failed: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut savegames = Vec::new();
+    for file_name in file_names {
+        if savegame_slot(&file_name).is_none() {
+            continue;
+        }
+        if let Ok(data) = storage
+            .load_file(&file_name, IW_DB_NAME, IW_FILE_STORE)
+            .await
+            && let Some(savegame) = SaveGame::new(file_name, data)
+        {
+            savegames.push(savegame);
         }
     }
+    savegames.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    savegames
+}
+
+fn load_config_file_promise(
+    ctx: &egui::Context,
+    storage: &impl Storage,
+    version: GameVersion,
+) -> Promise<Option<Vec<u8>>> {
+    let egui_ctx = ctx.clone();
+    let storage = storage.clone();
+    spawn_promise(async move {
+        let config_name = file_name(CONFIG_PREFIX, version);
+        let data = storage
+            .load_file(&config_name, IW_DB_NAME, IW_FILE_STORE)
+            .await
+            .ok();
+        egui_ctx.request_repaint(); // Wake ui thread
+        data
+    })
+}
+
+async fn upload_savegame(storage: &impl Storage) -> Result<String, String> {
+    let file = rfd::AsyncFileDialog::new()
+        .pick_file()
+        .await
+        .ok_or_else(|| "no file selected".to_string())?;
+
+    let file_name = normalise_savegame_name(&file.file_name()).ok_or_else(|| {
+        format!(
+            "{} is not a save game (SAVEGAM?.WL6, SAVEGAM?.SOD, ...)",
+            file.file_name()
+        )
+    })?;
+    let data = file.read().await;
+    store_savegame(storage, &file_name, &data)
+        .await
+        .map_err(|e| format!("storing {} failed: {}", file_name, e))?;
+    Ok(file_name)
+}
 
-    load_file_from_store(file_prefix, PLAYER_DB_NAME, PLAYER_STORE).await
+#[cfg(any(feature = "web", feature = "desktop"))]
+fn engine_config(
+    show_frame_rate: bool,
+    engine_settings: &EngineSettings,
+) -> Result<IWConfig, StartError> {
+    let mut iw_config =
+        default_iw_config().map_err(|e| StartError::new(StartStage::Config, format!("{:?}", e)))?;
+    engine_settings.apply(&mut iw_config);
+    iw_config.options.show_frame_rate = show_frame_rate;
+    Ok(iw_config)
 }
 
-async fn load_file_from_store(
-    file_prefix: &str,
-    db: &str,
-    store: &str,
-) -> (Option<Uint8Array>, usize) {
-    let file = &file_name(file_prefix, 6);
-    let file_6 = load_file_indexeddb(&file, db, store).await;
-    if file_6.is_ok() {
-        return (Some(file_6.unwrap()), 6);
+#[cfg(feature = "web")]
+async fn start_iw(
+    mut loader: Loader,
+    need_load_shareware: bool,
+    show_frame_rate: bool,
+    engine_settings: EngineSettings,
+) -> Result<(), StartError> {
+    let iw_config = engine_config(show_frame_rate, &engine_settings)?;
+    if need_load_shareware {
+        load_missing_shareware_data(&mut loader)
+            .await
+            .map_err(|e| StartError::new(StartStage::SharewareFetch, format!("{:?}", e)))?;
     }
 
-    // try WL3
-    let file = &file_name(file_prefix, 3);
-    let file_3 = load_file_indexeddb(&file, db, store).await;
-    if file_3.is_ok() {
-        return (Some(file_3.unwrap()), 3);
+    iw_start(loader, iw_config)
+        .map_err(|e| StartError::new(StartStage::EngineStart, format!("{:?}", e)))?;
+    Ok(())
+}
+
+pub async fn load_upload_state(storage: &impl Storage) -> UploadState {
+    let keys = match storage.list_files(PLAYER_DB_NAME, PLAYER_STORE).await {
+        Ok(keys) => migrate_legacy_files(storage, keys).await,
+        Err(e) => {
+            log::warn!("listing uploaded files failed: {}", e);
+            return UploadState::no_upload();
+        }
+    };
+
+    // group the files by installation (the part before the '/' in the key),
+    // each file as (key, prefix, version)
+    type Group = (String, Vec<(String, &'static str, GameVersion)>);
+    let mut groups: Vec<Group> = Vec::new();
+    for key in keys {
+        let Some((name, file)) = key.split_once('/') else {
+            continue;
+        };
+        let Some((prefix, version)) = normalise_data_file_name(file) else {
+            continue;
+        };
+        let name = name.to_string();
+        let entry = (key.clone(), prefix, version);
+        match groups.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, files)) => files.push(entry),
+            None => groups.push((name, vec![entry])),
+        }
     }
 
-    // try WL1
-    let file = &file_name(file_prefix, 1);
-    let file_1 = load_file_indexeddb(&file, db, store).await;
-    if file_1.is_ok() {
-        return (Some(file_1.unwrap()), 1);
+    let mut installations = Vec::with_capacity(groups.len());
+    for (name, files) in groups {
+        let versions: Vec<GameVersion> = files.iter().map(|(_, _, v)| *v).collect();
+        let Some(version) = preferred_version(&versions) else {
+            continue;
+        };
+
+        let mut installation = UploadStateFiles::new_empty(name, version);
+        for (key, prefix, file_version) in files {
+            if file_version != version {
+                continue;
+            }
+            if let Ok(data) = storage.load_file(&key, PLAYER_DB_NAME, PLAYER_STORE).await {
+                installation.set(prefix, data);
+            }
+        }
+        // the engine writes the config while playing, prefer its copy
+        let config = file_name(CONFIG_PREFIX, version);
+        if let Ok(data) = storage.load_file(&config, IW_DB_NAME, IW_FILE_STORE).await {
+            installation.config = Some(data);
+        }
+        installation.validate();
+        installation.identify_release();
+        installations.push(installation);
     }
+    installations.sort_by(|a, b| a.name.cmp(&b.name));
 
-    (None, 0)
+    let selected_name = storage
+        .load_text(SELECTED_INSTALLATION_KEY, PLAYER_DB_NAME, PLAYER_STORE)
+        .await
+        .ok();
+    let selected = match selected_name {
+        Some(name) => installations.iter().position(|i| i.name == name),
+        // nothing selected yet, the most preferred version wins
+        None => installations
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, i)| i.version)
+            .map(|(ix, _)| ix),
+    };
+
+    UploadState {
+        installations,
+        selected,
+    }
 }
 
-fn file_name(prefix: &str, version: usize) -> String {
-    format!("{}{}", prefix, version)
+/// Files uploaded before there were installations are stored without an
+/// installation prefix. They are moved into an installation named after
+/// their version. Returns the keys after the migration.
+async fn migrate_legacy_files(storage: &impl Storage, keys: Vec<String>) -> Vec<String> {
+    let mut result = Vec::with_capacity(keys.len());
+    for key in keys {
+        let legacy_version = if key.contains('/') {
+            None
+        } else {
+            normalise_data_file_name(&key).map(|(_, version)| version)
+        };
+        let Some(version) = legacy_version else {
+            result.push(key);
+            continue;
+        };
+
+        let new_key = installation_key(version.extension(), &key);
+        match move_file(storage, &key, &new_key).await {
+            Ok(()) => result.push(new_key),
+            Err(e) => log::warn!("migrating {} failed: {}", key, e),
+        }
+    }
+    result
 }
 
-fn register_ctrl_handler() {
+pub async fn load_settings(storage: &impl Storage) -> Settings {
+    match storage
+        .load_text(SETTINGS_KEY, PLAYER_DB_NAME, PLAYER_STORE)
+        .await
     {
-        let closure_keydown = Closure::wrap(Box::new(|e: web_sys::KeyboardEvent| {
-            if e.ctrl_key() && !e.shift_key() && !e.alt_key() && !e.meta_key() {
-                {
-                    let init = web_sys::KeyboardEventInit::new();
-                    init.set_ctrl_key(true);
-                    init.set_key(CONTROL_KEY);
-                    let event =
-                        KeyboardEvent::new_with_keyboard_event_init_dict(KEYDOWN_EVENT, &init)
-                            .expect("event");
-                    window()
-                        .unwrap()
-                        .document()
-                        .unwrap()
-                        .get_element_by_id("vga")
-                        .expect("Element not found")
-                        .dispatch_event(&event)
-                        .expect("event dispatch");
-                }
+        Ok(json) => Settings::from_json(&json),
+        Err(_) => Settings::default(), // nothing stored yet
+    }
+}
 
-                let closure = Closure::once(move || {
-                    let init = web_sys::KeyboardEventInit::new();
-                    init.set_ctrl_key(true);
-                    init.set_key(CONTROL_KEY);
-                    let event =
-                        KeyboardEvent::new_with_keyboard_event_init_dict(KEYUP_EVENT, &init)
-                            .expect("event");
-                    window()
-                        .unwrap()
-                        .document()
-                        .unwrap()
-                        .get_element_by_id("vga")
-                        .expect("Element not found")
-                        .dispatch_event(&event)
-                        .expect("event dispatch");
-                });
-                window()
-                    .unwrap()
-                    .set_timeout_with_callback_and_timeout_and_arguments_0(
-                        closure.as_ref().unchecked_ref(),
-                        KEYUP_DELAY_MS,
-                    )
-                    .unwrap();
-                closure.forget();
-            }
-        }) as Box<dyn FnMut(_)>);
-        window()
-            .unwrap()
-            .document()
-            .unwrap()
-            .add_event_listener_with_callback(
-                KEYDOWN_EVENT,
-                closure_keydown.as_ref().unchecked_ref(),
+fn store_settings(storage: &impl Storage, settings: &Settings) {
+    let json = settings.to_json();
+    let storage = storage.clone();
+    spawn_local(async move {
+        if let Err(e) = storage
+            .put_text(SETTINGS_KEY, &json, PLAYER_DB_NAME, PLAYER_STORE)
+            .await
+        {
+            log::warn!("storing the settings failed: {}", e);
+        }
+    });
+}
+
+/// The name an installation is stored under. It is the prefix of the keys
+/// of its files, a '/' would end it early.
+fn installation_name(name: &str) -> String {
+    name.trim().replace(['/', '\\'], "-")
+}
+
+fn installation_key(installation: &str, file_name: &str) -> String {
+    format!("{}/{}", installation, file_name)
+}
+
+fn store_selected_installation(storage: &impl Storage, name: String) {
+    let storage = storage.clone();
+    spawn_local(async move {
+        let result = storage
+            .put_text(
+                SELECTED_INSTALLATION_KEY,
+                &name,
+                PLAYER_DB_NAME,
+                PLAYER_STORE,
             )
-            .unwrap();
-        closure_keydown.forget();
-    }
+            .await;
+        if let Err(e) = result {
+            log::warn!("storing the selected installation failed: {}", e);
+        }
+    });
+}
+
+fn file_name(prefix: &str, version: GameVersion) -> String {
+    format!("{}{}", prefix, version.extension())
 }
 
 const MENUE_MIN_WDITH: f32 = 50.0;
@@ -823,10 +2307,619 @@ const BACKGROUND_COLOR: Color32 = egui::Color32::from_rgb(0x88, 0, 0);
 const MENU_BORDER_COLOUR_BOTTOM_RIGHT: Color32 = egui::Color32::from_rgb(0xD4, 0x00, 0x00);
 const ICON_COLOUR: Color32 = egui::Color32::from_rgb(0xFC, 0xFC, 0x54);
 
-fn file_upload_status(ui: &mut egui::Ui, file_name: &str, present: bool) {
+// with a modifier, so plain M still reaches the game (M-L-I cheat, save
+// game names)
+#[cfg(feature = "web")]
+const MUTE_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::M);
+
+#[cfg(feature = "web")]
+fn is_mute_shortcut(key: egui::Key, modifiers: egui::Modifiers) -> bool {
+    key == MUTE_SHORTCUT.logical_key && modifiers.matches_exact(MUTE_SHORTCUT.modifiers)
+}
+
+fn engine_settings_widget(ui: &mut egui::Ui, engine: &mut EngineSettings) {
+    let checkboxes = [
+        (
+            &mut engine.vanilla,
+            "Vanilla",
+            "play like the original game",
+        ),
+        (
+            &mut engine.options.no_wait,
+            "No wait",
+            "skip the waits in the menus and screens",
+        ),
+        (
+            &mut engine.options.fast_loading,
+            "Fast loading",
+            "skip the loading screens",
+        ),
+        (
+            &mut engine.options.enable_debug,
+            "Enable debug",
+            "allow the debug keys",
+        ),
+        (
+            &mut engine.options.fullscreen,
+            "Fullscreen",
+            "start the game fullscreen",
+        ),
+    ];
+    for (value, label, hint) in checkboxes {
+        ui.horizontal(|ui| {
+            ui.add_space(25.0);
+            ui.checkbox(value, RichText::new(label).color(ICON_COLOUR))
+                .on_hover_text(hint);
+        });
+    }
     ui.horizontal(|ui| {
         ui.add_space(25.0);
-        if present {
+        if ui.button("Reset to defaults").clicked() {
+            *engine = EngineSettings::default();
+        }
+    });
+    for (option, hint) in UNSUPPORTED_OPTIONS {
+        ui.horizontal(|ui| {
+            ui.add_space(25.0);
+            ui.label(
+                RichText::new(format!("{}: {}", option, hint))
+                    .size(10.0)
+                    .color(ICON_COLOUR),
+            );
+        });
+    }
+}
+
+/// Returns true while a value is dragged or typed.
+fn config_file_widgets(ui: &mut egui::Ui, config_file: &mut ConfigFile) -> bool {
+    mode_select(
+        ui,
+        "sound_mode",
+        "Sound effects",
+        &SOUND_MODES,
+        &mut config_file.sound_mode,
+    );
+    mode_select(
+        ui,
+        "music_mode",
+        "Music",
+        &MUSIC_MODES,
+        &mut config_file.music_mode,
+    );
+    mode_select(
+        ui,
+        "digi_mode",
+        "Digitized sound",
+        &DIGI_MODES,
+        &mut config_file.digi_mode,
+    );
+
+    // dragged values and typed names are stored once the edit is finished
+    let is_editing = |response: &egui::Response| response.dragged() || response.has_focus();
+    let mut editing = false;
+
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.label(RichText::new("View size").color(ICON_COLOUR));
+        let response =
+            ui.add(egui::DragValue::new(&mut config_file.view_size).range(VIEW_SIZE_RANGE));
+        editing |= is_editing(&response);
+    });
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.checkbox(
+            &mut config_file.mouse_enabled,
+            RichText::new("Mouse enabled").color(ICON_COLOUR),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.label(RichText::new("Mouse sensitivity").color(ICON_COLOUR));
+        let response = ui.add(
+            egui::DragValue::new(&mut config_file.mouse_adjustment).range(MOUSE_ADJUSTMENT_RANGE),
+        );
+        editing |= is_editing(&response);
+    });
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.checkbox(
+            &mut config_file.joystick_enabled,
+            RichText::new("Joystick enabled").color(ICON_COLOUR),
+        );
+    });
+
+    egui::CollapsingHeader::new(RichText::new("Keyboard").color(ICON_COLOUR))
+        .id_salt("config_keyboard")
+        .show(ui, |ui| {
+            for (ix, name) in DIRECTIONS.iter().enumerate() {
+                scan_code_select(ui, ("dir_scan", ix), name, &mut config_file.dir_scan[ix]);
+            }
+            for (ix, name) in BUTTONS.iter().enumerate() {
+                scan_code_select(
+                    ui,
+                    ("button_scan", ix),
+                    name,
+                    &mut config_file.button_scan[ix],
+                );
+            }
+        });
+
+    egui::CollapsingHeader::new(RichText::new("Mouse and joystick buttons").color(ICON_COLOUR))
+        .id_salt("config_buttons")
+        .show(ui, |ui| {
+            for ix in 0..config_file.button_mouse.len() {
+                let label = format!("Mouse button {}", ix + 1);
+                button_action_select(
+                    ui,
+                    ("button_mouse", ix),
+                    &label,
+                    &mut config_file.button_mouse[ix],
+                );
+            }
+            // unlike the mouse buttons the engine reads these as scan codes
+            for ix in 0..config_file.button_joy.len() {
+                let label = format!("Joystick button {}", ix + 1);
+                scan_code_select(
+                    ui,
+                    ("button_joy", ix),
+                    &label,
+                    &mut config_file.button_joy[ix],
+                );
+            }
+        });
+
+    egui::CollapsingHeader::new(RichText::new("High scores").color(ICON_COLOUR))
+        .id_salt("config_high_scores")
+        .show(ui, |ui| {
+            for score in &mut config_file.high_scores {
+                ui.horizontal(|ui| {
+                    ui.add_space(25.0);
+                    let response =
+                        ui.add(egui::TextEdit::singleline(&mut score.name).desired_width(100.0));
+                    editing |= is_editing(&response);
+                    ui.label(
+                        RichText::new(format!("{} (E{})", score.score, score.episode + 1))
+                            .color(ICON_COLOUR),
+                    );
+                });
+            }
+        });
+    editing
+}
+
+fn mode_select(ui: &mut egui::Ui, id: &str, label: &str, modes: &[&str], mode: &mut u16) {
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.label(RichText::new(label).color(ICON_COLOUR));
+        let selected_text = modes.get(*mode as usize).copied().unwrap_or("Unknown");
+        egui::ComboBox::from_id_salt(id)
+            .selected_text(selected_text)
+            .show_ui(ui, |ui| {
+                for (ix, name) in modes.iter().enumerate() {
+                    ui.selectable_value(mode, ix as u16, *name);
+                }
+            });
+    });
+}
+
+fn scan_code_select(ui: &mut egui::Ui, id: impl std::hash::Hash, label: &str, scan_code: &mut i16) {
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.label(RichText::new(label).color(ICON_COLOUR));
+        egui::ComboBox::from_id_salt(id)
+            .selected_text(scan_code_name(*scan_code))
+            .show_ui(ui, |ui| {
+                for code in bindable_scan_codes() {
+                    ui.selectable_value(scan_code, code, scan_code_name(code));
+                }
+            });
+    });
+}
+
+fn button_action_select(
+    ui: &mut egui::Ui,
+    id: impl std::hash::Hash,
+    label: &str,
+    action: &mut i16,
+) {
+    let action_name = |action: i16| {
+        usize::try_from(action)
+            .ok()
+            .and_then(|ix| BUTTON_ACTIONS.get(ix))
+            .copied()
+            .unwrap_or("None")
+    };
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.label(RichText::new(label).color(ICON_COLOUR));
+        egui::ComboBox::from_id_salt(id)
+            .selected_text(action_name(*action))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(action, -1, action_name(-1));
+                for ix in 0..BUTTON_ACTIONS.len() as i16 {
+                    ui.selectable_value(action, ix, action_name(ix));
+                }
+            });
+    });
+}
+
+#[cfg(feature = "web")]
+fn gamepad_settings_widget(ui: &mut egui::Ui, gamepad: &mut GamepadSettings) {
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.checkbox(
+            &mut gamepad.enabled,
+            RichText::new("Enabled").color(ICON_COLOUR),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.label(RichText::new("Deadzone").color(ICON_COLOUR));
+        ui.add(egui::Slider::new(&mut gamepad.deadzone, 0.05..=0.9));
+    });
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.label(
+            RichText::new("Left stick moves and turns, right stick turns")
+                .size(10.0)
+                .color(ICON_COLOUR),
+        );
+    });
+
+    for (button, name) in GAMEPAD_BUTTONS.iter().enumerate() {
+        let mut key = gamepad.key(button).map(str::to_string);
+        ui.horizontal(|ui| {
+            ui.add_space(25.0);
+            ui.label(RichText::new(*name).color(ICON_COLOUR));
+            let key_text = key.as_deref().map_or("None", game_key_action);
+            egui::ComboBox::from_id_salt(("gamepad_button", button))
+                .selected_text(key_text)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut key, None, "None");
+                    for (game_key, action) in GAME_KEYS {
+                        ui.selectable_value(&mut key, Some(game_key.to_string()), action);
+                    }
+                });
+        });
+        if key.as_deref() != gamepad.key(button) {
+            gamepad.set_key(button, key);
+        }
+    }
+}
+
+#[cfg(feature = "web")]
+fn touch_settings_widget(ui: &mut egui::Ui, touch: &mut TouchSettings) {
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.label(RichText::new("Show").color(ICON_COLOUR));
+        egui::ComboBox::from_id_salt("touch_mode")
+            .selected_text(touch.mode.title())
+            .show_ui(ui, |ui| {
+                for mode in TouchMode::ALL {
+                    ui.selectable_value(&mut touch.mode, mode, mode.title());
+                }
+            });
+    });
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.label(RichText::new("Opacity").color(ICON_COLOUR));
+        ui.add(egui::Slider::new(&mut touch.opacity, 0.1..=1.0));
+    });
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.label(RichText::new("Size").color(ICON_COLOUR));
+        ui.add(egui::Slider::new(&mut touch.size, 20.0..=64.0));
+    });
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.checkbox(
+            &mut touch.left_handed,
+            RichText::new("Stick on the right").color(ICON_COLOUR),
+        );
+    });
+}
+
+#[cfg(feature = "web")]
+fn audio_settings_widget(ui: &mut egui::Ui, audio: &mut AudioSettings) {
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.label(RichText::new("Music").color(ICON_COLOUR));
+        ui.add(egui::Slider::new(&mut audio.music_volume, 0.0..=1.0).show_value(false));
+    });
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.label(RichText::new("Sound effects").color(ICON_COLOUR));
+        ui.add(egui::Slider::new(&mut audio.sfx_volume, 0.0..=1.0).show_value(false));
+    });
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.checkbox(&mut audio.muted, RichText::new("Mute").color(ICON_COLOUR));
+    });
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.label(
+            RichText::new(format!(
+                "{} toggles mute while playing",
+                ui.ctx().format_shortcut(&MUTE_SHORTCUT)
+            ))
+            .size(10.0)
+            .color(ICON_COLOUR),
+        );
+    });
+}
+
+#[cfg(feature = "web")]
+fn jukebox_controls(ui: &mut egui::Ui, jukebox: &mut Jukebox) {
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        let button = |ui: &mut egui::Ui, icon: &str| {
+            ui.add(
+                egui::Button::new(RichText::new(icon).size(16.0).color(ICON_COLOUR)).frame(false),
+            )
+            .clicked()
+        };
+        if button(ui, egui_phosphor::regular::SKIP_BACK) {
+            jukebox.skip(-1);
+        }
+        let playing = jukebox.current.is_some() && !jukebox.paused;
+        let play_icon = if playing {
+            egui_phosphor::regular::PAUSE
+        } else {
+            egui_phosphor::regular::PLAY
+        };
+        if button(ui, play_icon) {
+            if jukebox.current.is_some() {
+                jukebox.toggle_pause();
+            } else {
+                jukebox.play(0);
+            }
+        }
+        if button(ui, egui_phosphor::regular::STOP) {
+            jukebox.stop();
+        }
+        if button(ui, egui_phosphor::regular::SKIP_FORWARD) {
+            jukebox.skip(1);
+        }
+        ui.checkbox(
+            &mut jukebox.looping,
+            RichText::new("Loop").color(ICON_COLOUR),
+        );
+    });
+
+    if let Some(track) = jukebox.current.and_then(|ix| jukebox.tracks.get(ix)) {
+        ui.horizontal(|ui| {
+            ui.add_space(25.0);
+            ui.label(
+                RichText::new(format!(
+                    "{} {} / {}",
+                    track.name,
+                    format_play_time(jukebox.position()),
+                    format_play_time(track.duration())
+                ))
+                .size(10.0)
+                .color(ICON_COLOUR),
+            );
+        });
+    }
+}
+
+#[cfg(feature = "web")]
+fn export_button(ui: &mut egui::Ui, enabled: bool) -> bool {
+    ui.add_enabled(
+        enabled,
+        egui::Button::new(
+            RichText::new(egui_phosphor::regular::DOWNLOAD_SIMPLE)
+                .size(16.0)
+                .color(ICON_COLOUR),
+        )
+        .frame(false),
+    )
+    .on_hover_text("Export as WAV")
+    .clicked()
+}
+
+#[cfg(feature = "web")]
+fn format_play_time(seconds: f64) -> String {
+    let seconds = seconds as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(feature = "web")]
+/// The shareware audio for the jukebox, the browser fetches it like the
+/// engine does.
+async fn load_shareware_audio() -> Result<(Vec<u8>, Vec<u8>), String> {
+    let audiohed = fetch_shareware_file("AUDIOHED.WL1").await?;
+    let audiot = fetch_shareware_file("AUDIOT.WL1").await?;
+    Ok((audiohed, audiot))
+}
+
+#[cfg(feature = "web")]
+async fn fetch_shareware_file(name: &str) -> Result<Vec<u8>, String> {
+    let url = format!("shareware/{}", name);
+    let response = gloo_net::http::Request::get(&url)
+        .send()
+        .await
+        .map_err(|e| format!("fetching {} failed: {}", url, e))?;
+    if !response.ok() {
+        return Err(format!("fetching {} failed: {}", url, response.status()));
+    }
+    response
+        .binary()
+        .await
+        .map_err(|e| format!("fetching {} failed: {}", url, e))
+}
+
+#[cfg(feature = "web")]
+fn mouse_look_settings_widget(ui: &mut egui::Ui, mouse_look: &mut MouseLookSettings) {
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.checkbox(
+            &mut mouse_look.enabled,
+            RichText::new("Capture the mouse").color(ICON_COLOUR),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.label(
+            RichText::new("Click the game to capture, Esc releases")
+                .size(10.0)
+                .color(ICON_COLOUR),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.label(RichText::new("Sensitivity").color(ICON_COLOUR));
+        ui.add(egui::Slider::new(&mut mouse_look.sensitivity, 0.1..=5.0));
+    });
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.checkbox(
+            &mut mouse_look.vertical_move,
+            RichText::new("Move forward/backward").color(ICON_COLOUR),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.add_enabled(
+            mouse_look.vertical_move,
+            egui::Checkbox::new(
+                &mut mouse_look.invert_y,
+                RichText::new("Invert").color(ICON_COLOUR),
+            ),
+        );
+    });
+}
+
+#[cfg(feature = "web")]
+fn key_bindings_widget(ui: &mut egui::Ui, key_bindings: &mut KeyBindings) {
+    let before = key_bindings.clone();
+
+    let mut preset = key_bindings.preset;
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.label(RichText::new("Preset").color(ICON_COLOUR));
+        egui::ComboBox::from_id_salt("key_preset")
+            .selected_text(preset.title())
+            .show_ui(ui, |ui| {
+                for option in KeyPreset::ALL {
+                    ui.selectable_value(&mut preset, option, option.title());
+                }
+            });
+    });
+    if preset != key_bindings.preset {
+        *key_bindings = KeyBindings::from_preset(preset);
+        return;
+    }
+
+    let inputs = binding_inputs();
+    let mut remove = None;
+    for (ix, binding) in key_bindings.bindings.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.add_space(25.0);
+            egui::ComboBox::from_id_salt(("binding_input", ix))
+                .width(80.0)
+                .selected_text(key_label(&binding.input))
+                .show_ui(ui, |ui| {
+                    for input in &inputs {
+                        ui.selectable_value(
+                            &mut binding.input,
+                            input.to_string(),
+                            key_label(input),
+                        );
+                    }
+                });
+            ui.label(RichText::new(egui_phosphor::regular::ARROW_RIGHT).color(ICON_COLOUR));
+            let key_text = game_key_action(&binding.key);
+            egui::ComboBox::from_id_salt(("binding_key", ix))
+                .width(80.0)
+                .selected_text(key_text)
+                .show_ui(ui, |ui| {
+                    for (key, action) in GAME_KEYS {
+                        ui.selectable_value(&mut binding.key, key.to_string(), action);
+                    }
+                });
+            if ui
+                .add(
+                    egui::Button::new(RichText::new(egui_phosphor::regular::X).color(ICON_COLOUR))
+                        .frame(false),
+                )
+                .clicked()
+            {
+                remove = Some(ix);
+            }
+        });
+    }
+    if let Some(ix) = remove {
+        key_bindings.bindings.remove(ix);
+    }
+
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        if ui.button("Add binding").clicked() {
+            let unbound = inputs
+                .iter()
+                .find(|input| key_bindings.lookup(input).is_none())
+                .unwrap_or(&MOUSE_PRIMARY);
+            key_bindings.bindings.push(KeyBinding {
+                input: unbound.to_string(),
+                key: GAME_KEYS[0].0.to_string(),
+            });
+        }
+    });
+
+    if *key_bindings != before {
+        key_bindings.preset = KeyPreset::Custom;
+    }
+}
+
+#[cfg(feature = "web")]
+/// What the key does in the game, the key name for other keys.
+fn game_key_action(key: &str) -> &str {
+    GAME_KEYS
+        .iter()
+        .find(|(game_key, _)| *game_key == key)
+        .map_or(key_label(key), |(_, action)| *action)
+}
+
+#[cfg(feature = "web")]
+/// Everything that can be bound: mouse buttons, the modifier keys (see
+/// register_modifier_handler) and the keys egui reports.
+fn binding_inputs() -> Vec<&'static str> {
+    MOUSE_INPUTS
+        .into_iter()
+        .chain(MODIFIER_KEYS)
+        .chain(egui::Key::ALL.iter().map(egui_key_to_event_key))
+        .collect()
+}
+
+#[cfg(feature = "web")]
+fn pointer_button_input(button: egui::PointerButton) -> &'static str {
+    match button {
+        egui::PointerButton::Primary => MOUSE_PRIMARY,
+        egui::PointerButton::Secondary => MOUSE_SECONDARY,
+        _ => MOUSE_MIDDLE,
+    }
+}
+
+fn game_title(ui: &mut egui::Ui, version: GameVersion) {
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.label(RichText::new(version.title()).strong().color(ICON_COLOUR));
+    });
+}
+
+fn file_upload_status(ui: &mut egui::Ui, file_name: &str, present: bool, error: Option<&str>) {
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        if let Some(error) = error {
+            ui.label(
+                RichText::new(egui_phosphor::regular::WARNING)
+                    .size(16.0)
+                    .color(ICON_COLOUR),
+            )
+            .on_hover_text(error);
+        } else if present {
             ui.label(
                 RichText::new(egui_phosphor::regular::CHECK_FAT)
                     .size(16.0)
@@ -841,10 +2934,52 @@ fn file_upload_status(ui: &mut egui::Ui, file_name: &str, present: bool) {
         }
         ui.label(RichText::new(file_name).color(ICON_COLOUR));
     });
+    if let Some(error) = error {
+        ui.horizontal(|ui| {
+            ui.add_space(45.0);
+            ui.label(RichText::new(error).size(10.0).color(ICON_COLOUR));
+        });
+    }
+}
+
+fn release_label(ui: &mut egui::Ui, release: &Fingerprint) {
+    let text = match release {
+        Fingerprint::Release(name) => name,
+        // no checksums of the retail releases are known yet
+        Fingerprint::Unknown => "release not identified, only the shareware is known",
+    };
+    ui.label(RichText::new(text).size(10.0).color(ICON_COLOUR));
+}
+
+fn upload_report_entry(ui: &mut egui::Ui, entry: &UploadReportEntry) {
+    let (icon, text) = match &entry.outcome {
+        UploadOutcome::Accepted => (egui_phosphor::regular::CHECK, entry.name.clone()),
+        UploadOutcome::Renamed(name) => (
+            egui_phosphor::regular::ARROW_RIGHT,
+            format!("{} → {}", entry.name, name),
+        ),
+        UploadOutcome::Ignored(reason) => (
+            egui_phosphor::regular::PROHIBIT,
+            format!("{}: {}", entry.name, reason),
+        ),
+        UploadOutcome::NotStored(e) => (
+            egui_phosphor::regular::WARNING,
+            format!(
+                "{}: only loaded for this session, storing failed: {}",
+                entry.name, e
+            ),
+        ),
+    };
+    ui.horizontal(|ui| {
+        ui.add_space(25.0);
+        ui.label(RichText::new(icon).size(10.0).color(ICON_COLOUR));
+        ui.label(RichText::new(text).size(10.0).color(ICON_COLOUR));
+    });
 }
 
 // unfortunately egui does not translate with name() to valid
 // js event key names. Some of them have to be corrected.
+#[cfg(feature = "web")]
 fn egui_key_to_event_key(key: &egui::Key) -> &str {
     match key {
         egui::Key::ArrowDown => "ArrowDown",
@@ -858,119 +2993,199 @@ fn egui_key_to_event_key(key: &egui::Key) -> &str {
 
 /// Always stores files in the PLAYER store as only IW should write
 /// files in the IW_DB.
-async fn store_file_indexeddb(file_name: &str, data: Uint8Array) -> Result<(), JsValue> {
-    let db = open_db(PLAYER_DB_NAME, PLAYER_STORE).await?;
-    let transaction =
-        db.transaction_with_str_and_mode(PLAYER_STORE, web_sys::IdbTransactionMode::Readwrite)?;
+async fn store_file(storage: &impl Storage, file_name: &str, data: &[u8]) -> Result<(), String> {
+    storage
+        .put_file(file_name, data, PLAYER_DB_NAME, PLAYER_STORE)
+        .await
+}
 
-    let store = transaction.object_store(PLAYER_STORE)?;
-    idb_request_await(&store.put_with_key(&data, &file_name.into())?)
+/// Save games are the exception to the rule above: they have to end up
+/// in the IW_DB, otherwise the engine does not offer them in the load menu.
+async fn store_savegame(
+    storage: &impl Storage,
+    file_name: &str,
+    data: &[u8],
+) -> Result<(), String> {
+    storage
+        .put_file(file_name, data, IW_DB_NAME, IW_FILE_STORE)
         .await
-        .map_err(|_| "idb store failed")?;
-    Ok(())
 }
 
-async fn reset_files_indexeddb() -> Result<(), JsValue> {
-    let db = open_db(PLAYER_DB_NAME, PLAYER_STORE).await?;
-    let transaction =
-        db.transaction_with_str_and_mode(PLAYER_STORE, web_sys::IdbTransactionMode::Readwrite)?;
+/// The CONFIG is the other exception, the engine reads and writes its
+/// copy in the IW_DB.
+async fn store_config(storage: &impl Storage, file_name: &str, data: &[u8]) -> Result<(), String> {
+    storage
+        .put_file(file_name, data, IW_DB_NAME, IW_FILE_STORE)
+        .await
+}
+
+async fn move_file(storage: &impl Storage, from: &str, to: &str) -> Result<(), String> {
+    let data = storage
+        .load_file(from, PLAYER_DB_NAME, PLAYER_STORE)
+        .await?;
+    store_file(storage, to, &data).await?;
+    storage
+        .delete_file(from, PLAYER_DB_NAME, PLAYER_STORE)
+        .await
+}
 
-    let store = transaction.object_store(PLAYER_STORE)?;
-    store.clear()?;
+async fn delete_installation(storage: &impl Storage, installation: &str) -> Result<(), String> {
+    let prefix = installation_key(installation, "");
+    for key in storage.list_files(PLAYER_DB_NAME, PLAYER_STORE).await? {
+        if key.starts_with(&prefix) {
+            storage
+                .delete_file(&key, PLAYER_DB_NAME, PLAYER_STORE)
+                .await?;
+        }
+    }
     Ok(())
 }
 
-async fn load_file_indexeddb(
-    file_name: &str,
-    db: &str,
-    store: &str,
-) -> Result<Uint8Array, JsValue> {
-    let db = open_db(db, store).await?;
-    let transaction =
-        db.transaction_with_str_and_mode(store, web_sys::IdbTransactionMode::Readwrite)?;
-    let store = transaction.object_store(store)?;
-
-    let value = idb_request_await(&store.get(&file_name.into())?)
-        .await
-        .map_err(|_| "idb load failed")?;
-    if value.is_undefined() {
-        Err(JsValue::NULL)
-    } else {
-        let uint8_array = Uint8Array::new(&value);
-        Ok(uint8_array)
-    }
-}
-
-async fn open_db(db: &str, store: &str) -> Result<web_sys::IdbDatabase, JsValue> {
-    let window = web_sys::window().expect("global window access");
-    let factory = window.indexed_db().map_err(|e| e)?;
-    if let Some(factory) = factory {
-        let open_request = factory.open(db)?;
-
-        let db_promise = js_sys::Promise::new(&mut |resolve, reject| {
-            let onsuccess = Closure::wrap(Box::new(move |event: web_sys::Event| {
-                let db = web_sys::IdbDatabase::from(
-                    js_sys::Reflect::get(&event, &JsValue::from_str("target"))
-                        .unwrap()
-                        .dyn_into::<web_sys::IdbOpenDbRequest>()
-                        .unwrap()
-                        .result()
-                        .unwrap(),
-                );
-                resolve.call1(&JsValue::NULL, &db).unwrap();
-            }) as Box<dyn FnMut(_)>);
-
-            let onerror = Closure::wrap(Box::new(move |_event: web_sys::Event| {
-                let error = "opening IndexDB failed".into();
-                reject.call1(&JsValue::NULL, &error).unwrap();
-            }) as Box<dyn FnMut(_)>);
-
-            let onupgradeneeded = Closure::wrap(Box::new(move |event: web_sys::Event| {
-                let db = web_sys::IdbDatabase::from(
-                    js_sys::Reflect::get(&event, &JsValue::from_str("target"))
-                        .unwrap()
-                        .dyn_into::<web_sys::IdbOpenDbRequest>()
-                        .unwrap()
-                        .result()
-                        .unwrap(),
-                );
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
 
-                if !db.object_store_names().contains(store) {
-                    db.create_object_store(store).expect("created store");
-                }
-            }) as Box<dyn FnMut(_)>);
-
-            open_request.set_onsuccess(Some(onsuccess.as_ref().unchecked_ref()));
-            open_request.set_onerror(Some(onerror.as_ref().unchecked_ref()));
-            open_request.set_onupgradeneeded(Some(onupgradeneeded.as_ref().unchecked_ref()));
-            onsuccess.forget();
-            onerror.forget();
-            onupgradeneeded.forget();
-        });
+    fn put(storage: &MemoryStorage, keys: &[&str]) {
+        storage.put_keys(PLAYER_DB_NAME, PLAYER_STORE, keys);
+    }
 
-        let db = JsFuture::from(db_promise).await?;
-        let db = web_sys::IdbDatabase::from(db);
-        Ok(db)
-    } else {
-        Err("could not access IndexDB".into())
+    fn keys(storage: &MemoryStorage) -> Vec<String> {
+        storage.keys(PLAYER_DB_NAME, PLAYER_STORE)
     }
-}
 
-async fn idb_request_await(request: &web_sys::IdbRequest) -> Result<JsValue, JsValue> {
-    let promise = js_sys::Promise::new(&mut |resolve, reject| {
-        let on_success = Closure::once(move |_: web_sys::Event| {
-            resolve.call0(&JsValue::NULL).unwrap();
-        });
-        let on_error = Closure::once(move |e: JsValue| {
-            reject.call1(&JsValue::NULL, &e).unwrap();
-        });
+    #[test]
+    fn normalise_data_file_name_ignores_directories_and_case() {
+        assert_eq!(
+            normalise_data_file_name("wolf3d/vswap.wl6"),
+            Some((VSWAP_PREFIX, GameVersion::WL6))
+        );
+        assert_eq!(
+            normalise_data_file_name("GAMES\\SPEAR\\AudioT.Sod"),
+            Some((AUDIOT_PREFIX, GameVersion::SOD))
+        );
+        assert_eq!(normalise_data_file_name("README.TXT"), None);
+        assert_eq!(normalise_data_file_name("VSWAP.WL9"), None);
+    }
 
-        request.set_onsuccess(Some(on_success.as_ref().unchecked_ref()));
-        request.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+    #[test]
+    fn preferred_version_takes_the_majority_then_the_preference() {
+        use GameVersion::*;
+        assert_eq!(preferred_version(&[]), None);
+        assert_eq!(preferred_version(&[WL1, WL1, WL6]), Some(WL1));
+        assert_eq!(preferred_version(&[WL1, WL6]), Some(WL6));
+        assert_eq!(preferred_version(&[WL6, SOD, WL3]), Some(SOD));
+    }
 
-        on_success.forget();
-        on_error.forget();
-    });
-    JsFuture::from(promise).await?;
-    request.result()
+    #[test]
+    fn migrate_legacy_files_moves_them_into_an_installation() {
+        let storage = MemoryStorage::default();
+        put(&storage, &["VSWAP.WL6", "WL3/VSWAP.WL3", "notes.txt"]);
+
+        let migrated = pollster::block_on(migrate_legacy_files(&storage, keys(&storage)));
+
+        assert!(migrated.contains(&"WL6/VSWAP.WL6".to_string()));
+        assert!(migrated.contains(&"WL3/VSWAP.WL3".to_string()));
+        assert!(migrated.contains(&"notes.txt".to_string()));
+        let stored = keys(&storage);
+        assert!(!stored.contains(&"VSWAP.WL6".to_string()));
+        let data = storage.get("WL6/VSWAP.WL6", PLAYER_DB_NAME, PLAYER_STORE);
+        assert_eq!(data.unwrap(), b"VSWAP.WL6");
+    }
+
+    #[test]
+    fn load_upload_state_groups_files_by_installation() {
+        let storage = MemoryStorage::default();
+        put(
+            &storage,
+            &[
+                "WL6/VSWAP.WL6",
+                "WL6/GAMEMAPS.WL6",
+                "WL6/VSWAP.WL3", // outvoted, left out
+                "mod/VSWAP.WL6",
+                "WL3/MAPHEAD.WL3",
+            ],
+        );
+
+        let state = pollster::block_on(load_upload_state(&storage));
+
+        let names: Vec<&str> = state
+            .installations
+            .iter()
+            .map(|i| i.name.as_str())
+            .collect();
+        assert_eq!(names, ["WL3", "WL6", "mod"]);
+        let wl6 = &state.installations[1];
+        assert_eq!(wl6.version, GameVersion::WL6);
+        assert!(wl6.get(VSWAP_PREFIX).is_some());
+        assert!(wl6.get(GAMEMAPS_PREFIX).is_some());
+        assert!(wl6.get(AUDIOT_PREFIX).is_none());
+        // nothing selected yet, the most preferred version wins
+        assert_eq!(state.files().map(|f| f.version), Some(GameVersion::WL6));
+    }
+
+    #[test]
+    fn load_upload_state_selects_the_stored_installation() {
+        let storage = MemoryStorage::default();
+        put(&storage, &["WL6/VSWAP.WL6", "WL3/VSWAP.WL3"]);
+        storage.put(
+            SELECTED_INSTALLATION_KEY,
+            b"WL3",
+            PLAYER_DB_NAME,
+            PLAYER_STORE,
+        );
+
+        let state = pollster::block_on(load_upload_state(&storage));
+        assert_eq!(state.selected_name(), "WL3");
+
+        // an empty name is the shareware
+        storage.put(SELECTED_INSTALLATION_KEY, b"", PLAYER_DB_NAME, PLAYER_STORE);
+        let state = pollster::block_on(load_upload_state(&storage));
+        assert!(state.files().is_none());
+    }
+
+    #[test]
+    fn delete_installation_keeps_the_other_installations() {
+        let storage = MemoryStorage::default();
+        put(
+            &storage,
+            &["WL6/VSWAP.WL6", "WL6/GAMEMAPS.WL6", "WL6 2/VSWAP.WL6"],
+        );
+
+        pollster::block_on(delete_installation(&storage, "WL6")).unwrap();
+
+        assert_eq!(keys(&storage), ["WL6 2/VSWAP.WL6"]);
+    }
+
+    #[test]
+    fn load_savegames_lists_the_engine_savegames_by_name() {
+        let storage = MemoryStorage::default();
+        storage.put_keys(
+            IW_DB_NAME,
+            IW_FILE_STORE,
+            &["SAVEGAM3.WL6", "SAVEGAM1.WL6", "CONFIG.WL6"],
+        );
+        put(&storage, &["SAVEGAM2.WL6"]); // not where the engine looks
+
+        let savegames = pollster::block_on(load_savegames(&storage));
+
+        let names: Vec<&str> = savegames.iter().map(|s| s.file_name.as_str()).collect();
+        assert_eq!(names, ["SAVEGAM1.WL6", "SAVEGAM3.WL6"]);
+        assert_eq!(savegames[1].slot, 3);
+        assert_eq!(savegames[1].description, "SAVEGAM3.WL6");
+    }
+
+    #[test]
+    fn installation_for_upload_creates_named_installations() {
+        let mut state = UploadState::no_upload();
+        state.installation_for_upload(GameVersion::WL6, "");
+        state.installation_for_upload(GameVersion::WL6, "");
+        assert_eq!(state.installations.len(), 1);
+
+        let named = state.installation_for_upload(GameVersion::WL6, " a/b ");
+        assert_eq!(named.map(|f| f.name.clone()), Some("a-b".to_string()));
+        state.installation_for_upload(GameVersion::WL6, "a/b");
+        assert_eq!(state.selected_name(), "a-b 2");
+        assert_eq!(state.installations.len(), 3);
+    }
 }
@@ -0,0 +1,217 @@
+// The engine creates the OPL audio worklet (public/oplProcessor.js) on
+// its own. index.html keeps a handle on the worklet node in
+// `window.iw_opl_node`, through its port the player sends the volumes
+// and plays the music of the jukebox. It also keeps the audio context and
+// the error if the worklet could not be set up, the engine then goes on
+// without sound.
+
+#[cfg(feature = "web")]
+use js_sys::{Object, Reflect, Uint8Array};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "web")]
+use wasm_bindgen::prelude::*;
+#[cfg(feature = "web")]
+use wasm_bindgen_futures::JsFuture;
+#[cfg(feature = "web")]
+use web_sys::{AudioContextState, AudioWorkletNode, BaseAudioContext, window};
+
+#[cfg(feature = "web")]
+use crate::task::spawn_local;
+
+#[cfg(feature = "web")]
+const OPL_NODE_KEY: &str = "iw_opl_node";
+#[cfg(feature = "web")]
+const AUDIO_CONTEXT_KEY: &str = "iw_audio_context";
+#[cfg(feature = "web")]
+const AUDIO_ERROR_KEY: &str = "iw_audio_error";
+
+#[cfg(feature = "web")]
+#[derive(Clone, PartialEq)]
+pub enum AudioState {
+    // the game has not set up its sound yet
+    NotStarted,
+    // waiting for a user gesture
+    Suspended,
+    Running,
+    Failed(String),
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    // 0.0 to 1.0
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub muted: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> AudioSettings {
+        AudioSettings {
+            music_volume: 1.0,
+            sfx_volume: 1.0,
+            muted: false,
+        }
+    }
+}
+
+#[cfg(feature = "web")]
+#[derive(Default)]
+pub struct AudioOutput {
+    // the node the settings were sent to, the engine creates a new one
+    // with every start
+    node: Option<AudioWorkletNode>,
+    applied: Option<AudioSettings>,
+}
+
+#[cfg(feature = "web")]
+impl AudioOutput {
+    /// Sends the settings to the worklet if they changed since the last
+    /// call or the worklet is new.
+    pub fn apply(&mut self, settings: &AudioSettings) {
+        let Some(node) = opl_node() else {
+            return;
+        };
+        let same_node = self
+            .node
+            .as_ref()
+            .is_some_and(|applied| Object::is(applied, &node));
+        if same_node && self.applied.as_ref() == Some(settings) {
+            return;
+        }
+
+        if let Err(e) = post_volume(&node, settings) {
+            log::warn!("setting the volume failed: {:?}", e);
+            return;
+        }
+        self.node = Some(node);
+        self.applied = Some(settings.clone());
+    }
+}
+
+#[cfg(feature = "web")]
+pub fn opl_node() -> Option<AudioWorkletNode> {
+    window_value(OPL_NODE_KEY)?
+        .dyn_into::<AudioWorkletNode>()
+        .ok()
+}
+
+#[cfg(feature = "web")]
+fn audio_context() -> Option<BaseAudioContext> {
+    window_value(AUDIO_CONTEXT_KEY)?
+        .dyn_into::<BaseAudioContext>()
+        .ok()
+}
+
+#[cfg(feature = "web")]
+fn window_value(key: &str) -> Option<JsValue> {
+    let window: JsValue = window()?.into();
+    Reflect::get(&window, &JsValue::from_str(key)).ok()
+}
+
+#[cfg(feature = "web")]
+pub fn audio_state() -> AudioState {
+    if let Some(error) = window_value(AUDIO_ERROR_KEY).and_then(|e| e.as_string()) {
+        return AudioState::Failed(error);
+    }
+    match audio_context().map(|context| context.state()) {
+        None => AudioState::NotStarted,
+        Some(AudioContextState::Running) => AudioState::Running,
+        Some(AudioContextState::Suspended) => AudioState::Suspended,
+        Some(_) => AudioState::Failed("the audio context was closed".to_string()),
+    }
+}
+
+#[cfg(feature = "web")]
+/// Resumes the audio context on every click and key press, browsers
+/// only allow that while handling a user gesture.
+pub fn register_resume_handler() {
+    let Some(document) = window().and_then(|w| w.document()) else {
+        return;
+    };
+    let closure = Closure::wrap(Box::new(resume) as Box<dyn FnMut()>);
+    for event in ["pointerdown", "keydown"] {
+        document
+            .add_event_listener_with_callback(event, closure.as_ref().unchecked_ref())
+            .expect("resume listener");
+    }
+    closure.forget();
+}
+
+#[cfg(feature = "web")]
+/// Resumes the audio context if the browser suspended it.
+pub fn resume() {
+    let Some(context) = audio_context() else {
+        return;
+    };
+    if context.state() != AudioContextState::Suspended {
+        return;
+    }
+    match context.resume() {
+        Ok(promise) => spawn_local(async move {
+            if let Err(e) = JsFuture::from(promise).await {
+                log::warn!("resuming the audio failed: {:?}", e);
+            }
+        }),
+        Err(e) => log::warn!("resuming the audio failed: {:?}", e),
+    }
+}
+
+#[cfg(feature = "web")]
+/// Whether the engine has set up its audio yet.
+pub fn is_available() -> bool {
+    opl_node().is_some()
+}
+
+#[cfg(feature = "web")]
+/// Replaces the music that is playing with the IMF commands.
+pub fn play_imf(imf: &[u8]) {
+    post_command("play_imf", &[("data", Uint8Array::from(imf).into())]);
+}
+
+#[cfg(feature = "web")]
+pub fn stop_imf() {
+    post_command("stop_imf", &[]);
+}
+
+#[cfg(feature = "web")]
+pub fn pause_imf(paused: bool) {
+    post_command("pause_imf", &[("paused", paused.into())]);
+}
+
+#[cfg(feature = "web")]
+fn post_volume(node: &AudioWorkletNode, settings: &AudioSettings) -> Result<(), JsValue> {
+    post_message(
+        node,
+        "set_volume",
+        &[
+            ("music", settings.music_volume.into()),
+            ("sfx", settings.sfx_volume.into()),
+            ("muted", settings.muted.into()),
+        ],
+    )
+}
+
+#[cfg(feature = "web")]
+fn post_command(cmd: &str, fields: &[(&str, JsValue)]) {
+    let Some(node) = opl_node() else {
+        return;
+    };
+    if let Err(e) = post_message(&node, cmd, fields) {
+        log::warn!("sending {} to the audio worklet failed: {:?}", cmd, e);
+    }
+}
+
+#[cfg(feature = "web")]
+fn post_message(
+    node: &AudioWorkletNode,
+    cmd: &str,
+    fields: &[(&str, JsValue)],
+) -> Result<(), JsValue> {
+    let message = Object::new();
+    Reflect::set(&message, &"cmd".into(), &cmd.into())?;
+    for (name, value) in fields {
+        Reflect::set(&message, &(*name).into(), value)?;
+    }
+    node.port()?.post_message(&message)
+}
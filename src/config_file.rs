@@ -0,0 +1,299 @@
+// Reading and writing of the CONFIG.WLx (and .SOD, ...) file the game
+// keeps its high scores, sound modes, control bindings and view size in.
+// Layout (little endian, 16 bit ints as in the DOS original):
+//   7 high scores: name (58 bytes, zero terminated), score (i32),
+//                  completed (u16), episode (u16)
+//   sound mode, music mode, digi mode (u16 each)
+//   mouse, joystick, joypad enabled, joystick progressive (bool as u16),
+//   joystick port (i16)
+//   dir scan codes [4], button scan codes [8], mouse buttons [4],
+//   joystick buttons [4] (i16 each)
+//   view size, mouse adjustment (i16)
+
+pub const MAX_SCORES: usize = 7;
+const HIGH_NAME_LEN: usize = 58;
+const HIGH_SCORE_SIZE: usize = HIGH_NAME_LEN + 4 + 2 + 2;
+pub const CONFIG_FILE_SIZE: usize = MAX_SCORES * HIGH_SCORE_SIZE + 3 * 2 + 5 * 2 + 20 * 2 + 2 * 2;
+
+pub const SOUND_MODES: [&str; 3] = ["Off", "PC Speaker", "AdLib"];
+pub const MUSIC_MODES: [&str; 2] = ["Off", "AdLib"];
+pub const DIGI_MODES: [&str; 4] = ["Off", "PC Speaker", "Sound Source", "Sound Blaster"];
+pub const DIRECTIONS: [&str; 4] = ["Forward", "Right", "Backward", "Left"];
+pub const BUTTONS: [&str; 8] = [
+    "Attack",
+    "Strafe",
+    "Run",
+    "Use",
+    "Knife",
+    "Pistol",
+    "Machine gun",
+    "Chain gun",
+];
+// actions mouse and joystick buttons can be bound to, -1 is no action
+pub const BUTTON_ACTIONS: [&str; 4] = ["Attack", "Strafe", "Run", "Use"];
+pub const VIEW_SIZE_RANGE: std::ops::RangeInclusive<i16> = 4..=20;
+pub const MOUSE_ADJUSTMENT_RANGE: std::ops::RangeInclusive<i16> = 0..=9;
+
+#[derive(Clone, PartialEq)]
+pub struct HighScore {
+    pub name: String,
+    pub score: i32,
+    pub completed: u16,
+    pub episode: u16,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct ConfigFile {
+    pub high_scores: Vec<HighScore>,
+    pub sound_mode: u16,
+    pub music_mode: u16,
+    pub digi_mode: u16,
+    pub mouse_enabled: bool,
+    pub joystick_enabled: bool,
+    pub joypad_enabled: bool,
+    pub joystick_progressive: bool,
+    pub joystick_port: i16,
+    pub dir_scan: [i16; 4],
+    pub button_scan: [i16; 8],
+    // the button actions of the mouse buttons
+    pub button_mouse: [i16; 4],
+    // scan codes, the engine reads them like button_scan
+    pub button_joy: [i16; 4],
+    pub view_size: i16,
+    pub mouse_adjustment: i16,
+    // bytes after the known layout (written by other ports), kept as they are
+    rest: Vec<u8>,
+}
+
+impl ConfigFile {
+    pub fn parse(data: &[u8]) -> Result<ConfigFile, String> {
+        if data.len() < CONFIG_FILE_SIZE {
+            return Err(format!(
+                "has {} bytes, expected {}",
+                data.len(),
+                CONFIG_FILE_SIZE
+            ));
+        }
+
+        let mut reader = Reader { data, pos: 0 };
+        let mut high_scores = Vec::with_capacity(MAX_SCORES);
+        for _ in 0..MAX_SCORES {
+            let name = reader.bytes(HIGH_NAME_LEN);
+            let end = name.iter().position(|b| *b == 0).unwrap_or(name.len());
+            high_scores.push(HighScore {
+                name: String::from_utf8_lossy(&name[..end]).to_string(),
+                score: reader.i32(),
+                completed: reader.u16(),
+                episode: reader.u16(),
+            });
+        }
+
+        Ok(ConfigFile {
+            high_scores,
+            sound_mode: reader.u16(),
+            music_mode: reader.u16(),
+            digi_mode: reader.u16(),
+            mouse_enabled: reader.u16() != 0,
+            joystick_enabled: reader.u16() != 0,
+            joypad_enabled: reader.u16() != 0,
+            joystick_progressive: reader.u16() != 0,
+            joystick_port: reader.i16(),
+            dir_scan: reader.i16_array(),
+            button_scan: reader.i16_array(),
+            button_mouse: reader.i16_array(),
+            button_joy: reader.i16_array(),
+            view_size: reader.i16(),
+            mouse_adjustment: reader.i16(),
+            rest: data[CONFIG_FILE_SIZE..].to_vec(),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(CONFIG_FILE_SIZE + self.rest.len());
+        for ix in 0..MAX_SCORES {
+            let score = self.high_scores.get(ix);
+            let mut name = [0u8; HIGH_NAME_LEN];
+            if let Some(score) = score {
+                // keep the terminating zero, DOS names are plain ascii
+                let bytes: Vec<u8> = score
+                    .name
+                    .chars()
+                    .filter(char::is_ascii)
+                    .map(|c| c as u8)
+                    .collect();
+                let len = bytes.len().min(HIGH_NAME_LEN - 1);
+                name[..len].copy_from_slice(&bytes[..len]);
+            }
+            data.extend_from_slice(&name);
+            data.extend_from_slice(&score.map_or(0, |s| s.score).to_le_bytes());
+            data.extend_from_slice(&score.map_or(0, |s| s.completed).to_le_bytes());
+            data.extend_from_slice(&score.map_or(0, |s| s.episode).to_le_bytes());
+        }
+
+        for value in [self.sound_mode, self.music_mode, self.digi_mode] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [
+            self.mouse_enabled,
+            self.joystick_enabled,
+            self.joypad_enabled,
+            self.joystick_progressive,
+        ] {
+            data.extend_from_slice(&(value as u16).to_le_bytes());
+        }
+        let values = std::iter::once(self.joystick_port)
+            .chain(self.dir_scan)
+            .chain(self.button_scan)
+            .chain(self.button_mouse)
+            .chain(self.button_joy)
+            .chain([self.view_size, self.mouse_adjustment]);
+        for value in values {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(&self.rest);
+        data
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> &'a [u8] {
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        bytes
+    }
+
+    fn u16(&mut self) -> u16 {
+        let b = self.bytes(2);
+        u16::from_le_bytes([b[0], b[1]])
+    }
+
+    fn i16(&mut self) -> i16 {
+        self.u16() as i16
+    }
+
+    fn i32(&mut self) -> i32 {
+        let b = self.bytes(4);
+        i32::from_le_bytes([b[0], b[1], b[2], b[3]])
+    }
+
+    fn i16_array<const N: usize>(&mut self) -> [i16; N] {
+        let mut result = [0; N];
+        for value in result.iter_mut() {
+            *value = self.i16();
+        }
+        result
+    }
+}
+
+/// Name of a keyboard scan code, for the codes the game menus allow to bind.
+pub fn scan_code_name(scan_code: i16) -> String {
+    let name = match scan_code {
+        0x01 => "Esc",
+        0x02 => "1",
+        0x03 => "2",
+        0x04 => "3",
+        0x05 => "4",
+        0x06 => "5",
+        0x07 => "6",
+        0x08 => "7",
+        0x09 => "8",
+        0x0A => "9",
+        0x0B => "0",
+        0x0F => "Tab",
+        0x10 => "Q",
+        0x11 => "W",
+        0x12 => "E",
+        0x13 => "R",
+        0x14 => "T",
+        0x15 => "Y",
+        0x16 => "U",
+        0x17 => "I",
+        0x18 => "O",
+        0x19 => "P",
+        0x1C => "Enter",
+        0x1D => "Ctrl",
+        0x1E => "A",
+        0x1F => "S",
+        0x20 => "D",
+        0x21 => "F",
+        0x22 => "G",
+        0x23 => "H",
+        0x24 => "J",
+        0x25 => "K",
+        0x26 => "L",
+        0x2A => "Left Shift",
+        0x2C => "Z",
+        0x2D => "X",
+        0x2E => "C",
+        0x2F => "V",
+        0x30 => "B",
+        0x31 => "N",
+        0x32 => "M",
+        0x36 => "Right Shift",
+        0x38 => "Alt",
+        0x39 => "Space",
+        0x48 => "Up",
+        0x4B => "Left",
+        0x4D => "Right",
+        0x50 => "Down",
+        _ => return format!("0x{:02X}", scan_code),
+    };
+    name.to_string()
+}
+
+/// The scan codes offered for binding (the ones `scan_code_name` knows).
+pub fn bindable_scan_codes() -> impl Iterator<Item = i16> {
+    (0x01..=0x50).filter(|code| !scan_code_name(*code).starts_with("0x"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHAREWARE_CONFIG: &[u8] = include_bytes!("../public/shareware/CONFIG.WL1");
+
+    #[test]
+    fn has_the_size_of_the_dos_file() {
+        assert_eq!(CONFIG_FILE_SIZE, 522);
+        assert_eq!(SHAREWARE_CONFIG.len(), CONFIG_FILE_SIZE);
+    }
+
+    #[test]
+    fn writes_the_shareware_config_back_unchanged() {
+        let config = ConfigFile::parse(SHAREWARE_CONFIG).unwrap();
+        assert_eq!(config.high_scores.len(), MAX_SCORES);
+        assert!(VIEW_SIZE_RANGE.contains(&config.view_size));
+        assert_eq!(config.to_bytes(), SHAREWARE_CONFIG);
+    }
+
+    #[test]
+    fn writes_the_fields_at_their_offsets() {
+        let mut config = ConfigFile::parse(SHAREWARE_CONFIG).unwrap();
+        config.sound_mode = 2;
+        config.view_size = 15;
+        config.mouse_adjustment = 7;
+        let data = config.to_bytes();
+        // after the 7 high scores of 66 bytes
+        assert_eq!(data[462..464], [2, 0]);
+        assert_eq!(data[518..520], [15, 0]);
+        assert_eq!(data[520..522], [7, 0]);
+    }
+
+    #[test]
+    fn keeps_the_bytes_of_other_ports() {
+        let mut data = SHAREWARE_CONFIG.to_vec();
+        data.extend_from_slice(b"port");
+        let config = ConfigFile::parse(&data).unwrap();
+        assert_eq!(config.to_bytes(), data);
+    }
+
+    #[test]
+    fn rejects_a_short_file() {
+        assert!(ConfigFile::parse(&SHAREWARE_CONFIG[..CONFIG_FILE_SIZE - 1]).is_err());
+    }
+}
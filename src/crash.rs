@@ -0,0 +1,91 @@
+use std::panic::PanicHookInfo;
+
+use wasm_bindgen::JsValue;
+use web_sys::{Document, window};
+
+const CRASH_OVERLAY_ID: &str = "iw_crash_overlay";
+const CRASH_REPORT_ID: &str = "iw_crash_report";
+
+// The overlay is plain HTML: after a panic the wasm module must not be
+// entered again, so the copy button is wired up in JS only.
+const COPY_REPORT_JS: &str = "navigator.clipboard.writeText(document.getElementById('iw_crash_report').value); this.textContent = 'Copied';";
+const OVERLAY_STYLE: &str = "position: fixed; inset: 0; z-index: 1000; display: flex; flex-direction: column; align-items: center; justify-content: center; gap: 12px; background: rgba(0, 0, 0, 0.85); color: #fcfc54; font-family: Ubuntu-Light, Helvetica, sans-serif;";
+const REPORT_STYLE: &str = "width: 80%; max-width: 720px; height: 240px; background: #530000; color: #f0f0f0; border: 2px solid #d40000; font-family: monospace;";
+
+/// Installs a panic hook that renders a crash overlay with a bug report
+/// over the page. The previous hook (console logging) still runs.
+pub fn install_panic_hook() {
+    let previous_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        previous_hook(info);
+        show_crash_overlay(&crash_report(info));
+    }));
+}
+
+fn crash_report(info: &PanicHookInfo) -> String {
+    let message = if let Some(message) = info.payload().downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = info.payload().downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    };
+    let location = info
+        .location()
+        .map_or_else(|| "unknown".to_string(), |l| l.to_string());
+    let user_agent = window()
+        .and_then(|w| w.navigator().user_agent().ok())
+        .unwrap_or_default();
+
+    format!(
+        "iron-wolf-webplayer {}\nmessage: {}\nlocation: {}\nbrowser: {}",
+        env!("CARGO_PKG_VERSION"),
+        message,
+        location,
+        user_agent
+    )
+}
+
+fn show_crash_overlay(report: &str) {
+    let Some(document) = window().and_then(|w| w.document()) else {
+        return;
+    };
+    if document.get_element_by_id(CRASH_OVERLAY_ID).is_some() {
+        return; // only report the first panic
+    }
+    // ignore DOM errors, there is nothing left to report them to
+    let _ = append_crash_overlay(&document, report);
+}
+
+fn append_crash_overlay(document: &Document, report: &str) -> Result<(), JsValue> {
+    let body = document.body().ok_or("No body")?;
+
+    let overlay = document.create_element("div")?;
+    overlay.set_id(CRASH_OVERLAY_ID);
+    overlay.set_attribute("style", OVERLAY_STYLE)?;
+
+    let title = document.create_element("h2")?;
+    title.set_text_content(Some("Iron-Wolf has crashed"));
+    overlay.append_child(&title)?;
+
+    let hint = document.create_element("p")?;
+    hint.set_text_content(Some(
+        "Please send us the report below. Reload the page to play again.",
+    ));
+    overlay.append_child(&hint)?;
+
+    let text = document.create_element("textarea")?;
+    text.set_id(CRASH_REPORT_ID);
+    text.set_attribute("readonly", "")?;
+    text.set_attribute("style", REPORT_STYLE)?;
+    text.set_text_content(Some(report));
+    overlay.append_child(&text)?;
+
+    let copy = document.create_element("button")?;
+    copy.set_text_content(Some("Copy report"));
+    copy.set_attribute("onclick", COPY_REPORT_JS)?;
+    overlay.append_child(&copy)?;
+
+    body.append_child(&overlay)?;
+    Ok(())
+}
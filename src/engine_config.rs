@@ -0,0 +1,67 @@
+// The options of the engine config (iron-wolf's IWConfig) the user can
+// change in the settings panel. They are applied to the default config on
+// every start. The layout follows IWConfig, that keeps the settings
+// stored by earlier players readable. The frame rate has its own
+// setting (Settings::show_frame_rate).
+
+#[cfg(any(feature = "web", feature = "desktop"))]
+use iw::def::IWConfig;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EngineSettings {
+    // play like the original game, without the engine's additions
+    pub vanilla: bool,
+    pub options: EngineOptions,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EngineOptions {
+    pub no_wait: bool,
+    pub fast_loading: bool,
+    pub enable_debug: bool,
+    pub fullscreen: bool,
+}
+
+// the defaults of default_iw_config()
+impl Default for EngineSettings {
+    fn default() -> EngineSettings {
+        EngineSettings {
+            vanilla: true,
+            options: EngineOptions::default(),
+        }
+    }
+}
+
+impl Default for EngineOptions {
+    fn default() -> EngineOptions {
+        EngineOptions {
+            no_wait: false,
+            fast_loading: false,
+            enable_debug: false,
+            fullscreen: true,
+        }
+    }
+}
+
+impl EngineSettings {
+    #[cfg(any(feature = "web", feature = "desktop"))]
+    pub fn apply(&self, config: &mut IWConfig) {
+        config.vanilla = self.vanilla;
+        config.options.no_wait = self.options.no_wait;
+        config.options.fast_loading = self.options.fast_loading;
+        config.options.enable_debug = self.options.enable_debug;
+        config.options.fullscreen = self.options.fullscreen;
+    }
+}
+
+/// Game options users look for that the engine config does not have,
+/// with where they can be changed instead.
+pub const UNSUPPORTED_OPTIONS: [(&str, &str); 4] = [
+    ("Sound and music", "in the CONFIG file settings"),
+    ("Mouse sensitivity", "in the CONFIG file settings"),
+    ("View size", "in the CONFIG file settings"),
+    ("Default difficulty", "not supported by the engine"),
+];
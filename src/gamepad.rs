@@ -0,0 +1,193 @@
+// Gamepad input through the browser Gamepad API. The controllers are
+// polled every frame and their buttons and sticks turned into the keys
+// the engine understands (the same ones the keyboard forwarder sends).
+// Button numbers follow the "standard" mapping of the Gamepad API.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "web")]
+use wasm_bindgen::JsCast;
+#[cfg(feature = "web")]
+use web_sys::{Gamepad, GamepadButton, window};
+
+#[cfg(feature = "web")]
+use crate::key_bindings::key_changes;
+
+#[cfg(feature = "web")]
+pub const GAMEPAD_BUTTONS: [&str; 17] = [
+    "A",
+    "B",
+    "X",
+    "Y",
+    "LB",
+    "RB",
+    "LT",
+    "RT",
+    "Back",
+    "Start",
+    "Left stick",
+    "Right stick",
+    "D-pad up",
+    "D-pad down",
+    "D-pad left",
+    "D-pad right",
+    "Home",
+];
+
+// axes of the standard mapping
+#[cfg(feature = "web")]
+const LEFT_STICK_X: usize = 0;
+#[cfg(feature = "web")]
+const LEFT_STICK_Y: usize = 1;
+#[cfg(feature = "web")]
+const RIGHT_STICK_X: usize = 2;
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct GamepadBinding {
+    pub button: usize,
+    pub key: String,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadSettings {
+    pub enabled: bool,
+    // stick deflection (0..1) below which a stick counts as centred
+    pub deadzone: f32,
+    pub buttons: Vec<GamepadBinding>,
+}
+
+impl Default for GamepadSettings {
+    fn default() -> GamepadSettings {
+        let buttons = [
+            (0, " "),
+            (1, "Alt"),
+            (2, "2"),
+            (3, "3"),
+            (4, "Shift"),
+            (5, "4"),
+            (7, "Control"),
+            (8, "Enter"),
+            (9, "Escape"),
+            (12, "ArrowUp"),
+            (13, "ArrowDown"),
+            (14, "ArrowLeft"),
+            (15, "ArrowRight"),
+        ];
+        GamepadSettings {
+            enabled: true,
+            deadzone: 0.25,
+            buttons: buttons
+                .into_iter()
+                .map(|(button, key)| GamepadBinding {
+                    button,
+                    key: key.to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(feature = "web")]
+impl GamepadSettings {
+    pub fn key(&self, button: usize) -> Option<&str> {
+        self.buttons
+            .iter()
+            .find(|binding| binding.button == button)
+            .map(|binding| binding.key.as_str())
+    }
+
+    /// Binds the button to the key, None unbinds it.
+    pub fn set_key(&mut self, button: usize, key: Option<String>) {
+        self.buttons.retain(|binding| binding.button != button);
+        if let Some(key) = key {
+            self.buttons.push(GamepadBinding { button, key });
+            self.buttons.sort_by_key(|binding| binding.button);
+        }
+    }
+
+    /// The keys held down by the buttons and sticks. The left stick moves
+    /// and turns, the right stick turns.
+    fn held_keys(&self, pressed: &[bool], axes: &[f64]) -> Vec<String> {
+        let mut keys: Vec<String> = Vec::new();
+        let mut hold = |key: &str| {
+            if !keys.iter().any(|k| k == key) {
+                keys.push(key.to_string());
+            }
+        };
+
+        for binding in &self.buttons {
+            if pressed.get(binding.button).copied().unwrap_or(false) {
+                hold(&binding.key);
+            }
+        }
+
+        let deadzone = self.deadzone as f64;
+        let axis = |ix: usize| axes.get(ix).copied().unwrap_or(0.0);
+        if axis(LEFT_STICK_Y) < -deadzone {
+            hold("ArrowUp");
+        } else if axis(LEFT_STICK_Y) > deadzone {
+            hold("ArrowDown");
+        }
+        for x in [axis(LEFT_STICK_X), axis(RIGHT_STICK_X)] {
+            if x < -deadzone {
+                hold("ArrowLeft");
+            } else if x > deadzone {
+                hold("ArrowRight");
+            }
+        }
+        keys
+    }
+}
+
+#[cfg(feature = "web")]
+#[derive(Default)]
+pub struct GamepadState {
+    // id of the connected controller, the first one if there are more
+    pub connected: Option<String>,
+    held: Vec<String>,
+}
+
+#[cfg(feature = "web")]
+impl GamepadState {
+    /// Polls the first connected controller. Returns the keys that went
+    /// down (true) or up (false) since the last poll.
+    pub fn poll(&mut self, settings: &GamepadSettings) -> Vec<(String, bool)> {
+        let gamepad = first_gamepad();
+        self.connected = gamepad.as_ref().map(Gamepad::id);
+
+        let held = match &gamepad {
+            Some(gamepad) if settings.enabled => {
+                let pressed: Vec<bool> = gamepad
+                    .buttons()
+                    .iter()
+                    .map(|button| {
+                        button
+                            .dyn_into::<GamepadButton>()
+                            .is_ok_and(|button| button.pressed())
+                    })
+                    .collect();
+                let axes: Vec<f64> = gamepad
+                    .axes()
+                    .iter()
+                    .map(|axis| axis.as_f64().unwrap_or(0.0))
+                    .collect();
+                settings.held_keys(&pressed, &axes)
+            }
+            // release everything if the controller is gone or disabled
+            _ => Vec::new(),
+        };
+
+        let changes = key_changes(&self.held, &held);
+        self.held = held;
+        changes
+    }
+}
+
+#[cfg(feature = "web")]
+fn first_gamepad() -> Option<Gamepad> {
+    let gamepads = window()?.navigator().get_gamepads().ok()?;
+    gamepads
+        .iter()
+        .filter_map(|gamepad| gamepad.dyn_into::<Gamepad>().ok())
+        .find(Gamepad::connected)
+}
@@ -0,0 +1,285 @@
+// The music of the selected installation, played through the engine's
+// OPL worklet (see audio.rs). AUDIOT holds the same number of PC speaker,
+// AdLib and digitized sound chunks, followed by the music. The digitized
+// ones are empty (the samples are in VSWAP) and end with an "!ID!" marker.
+
+#[cfg(feature = "web")]
+use crate::audio;
+
+// the rate the IMF delays are counted in
+pub const IMF_CLOCK_RATE: f64 = 700.0;
+const ID_MARKER: &[u8] = b"!ID!";
+// length, priority, instrument and octave block
+const ADL_HEADER_SIZE: usize = 23;
+// the most play time one update counts, a throttled or hidden tab updates
+// seldom and would otherwise jump to the end of the track
+#[cfg(feature = "web")]
+const MAX_UPDATE_STEP: f64 = 0.25;
+
+pub struct MusicTrack {
+    pub chunk: usize,
+    pub name: String,
+    // the IMF commands (register, value, u16 delay), without the length
+    pub imf: Vec<u8>,
+}
+
+impl MusicTrack {
+    /// Play time of one pass in seconds.
+    pub fn duration(&self) -> f64 {
+        let ticks: u64 = self
+            .imf
+            .chunks_exact(4)
+            .map(|command| u16::from_le_bytes([command[2], command[3]]) as u64)
+            .sum();
+        ticks as f64 / IMF_CLOCK_RATE
+    }
+}
+
+/// The chunks of AUDIOT as given by the offsets in AUDIOHED.
+pub fn audio_chunks<'a>(audiohed: &[u8], audiot: &'a [u8]) -> Result<Vec<&'a [u8]>, String> {
+    let offsets: Vec<usize> = audiohed
+        .chunks_exact(4)
+        .map(|entry| u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]) as usize)
+        .collect();
+    offsets
+        .windows(2)
+        .enumerate()
+        .map(|(chunk, range)| {
+            audiot
+                .get(range[0]..range[1])
+                .ok_or_else(|| format!("chunk {} is outside of AUDIOT", chunk))
+        })
+        .collect()
+}
+
+pub struct SoundEffect {
+    pub chunk: usize,
+    pub name: String,
+    // the whole chunk, as the engine plays it
+    pub adl: Vec<u8>,
+}
+
+pub struct GameAudio {
+    pub tracks: Vec<MusicTrack>,
+    pub sounds: Vec<SoundEffect>,
+}
+
+pub fn parse_audio(audiohed: &[u8], audiot: &[u8]) -> Result<GameAudio, String> {
+    let chunks = audio_chunks(audiohed, audiot)?;
+    let music_start = chunks
+        .iter()
+        .rposition(|chunk| chunk.is_empty() || *chunk == ID_MARKER)
+        .map(|ix| ix + 1)
+        .ok_or("no music found")?;
+    Ok(GameAudio {
+        tracks: parse_music(&chunks, music_start),
+        sounds: parse_sound_effects(&chunks, music_start / 3),
+    })
+}
+
+/// All music tracks that have data, the shareware only keeps the names
+/// of most of them.
+fn parse_music(chunks: &[&[u8]], music_start: usize) -> Vec<MusicTrack> {
+    let mut tracks = Vec::new();
+    for (chunk, data) in chunks.iter().enumerate().skip(music_start) {
+        if data.len() < 2 {
+            continue;
+        }
+        let len = u16::from_le_bytes([data[0], data[1]]) as usize;
+        if len == 0 || 2 + len > data.len() {
+            continue;
+        }
+        // the IMF data is followed by a tag count and the track name
+        let name = data
+            .get(2 + len + 2..)
+            .and_then(track_name)
+            .unwrap_or_else(|| format!("Track {}", tracks.len() + 1));
+        tracks.push(MusicTrack {
+            chunk,
+            name,
+            imf: data[2..2 + len].to_vec(),
+        });
+    }
+    tracks
+}
+
+/// The AdLib sounds, they follow the PC speaker ones.
+fn parse_sound_effects(chunks: &[&[u8]], num_sounds: usize) -> Vec<SoundEffect> {
+    let mut sounds = Vec::new();
+    for (chunk, data) in chunks.iter().enumerate().skip(num_sounds).take(num_sounds) {
+        if data.len() < ADL_HEADER_SIZE {
+            continue;
+        }
+        let len = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        // the sound data is followed by a terminator and the name
+        let name = data
+            .get(ADL_HEADER_SIZE + len + 1..)
+            .and_then(track_name)
+            .unwrap_or_else(|| format!("Sound {}", chunk - num_sounds + 1));
+        sounds.push(SoundEffect {
+            chunk,
+            name,
+            adl: data.to_vec(),
+        });
+    }
+    sounds
+}
+
+fn track_name(data: &[u8]) -> Option<String> {
+    let end = data.iter().position(|b| *b == 0)?;
+    let name = &data[..end];
+    (!name.is_empty() && name.iter().all(|b| b.is_ascii_graphic()))
+        .then(|| String::from_utf8_lossy(name).to_string())
+}
+
+#[cfg(feature = "web")]
+#[derive(Default)]
+pub struct Jukebox {
+    pub tracks: Vec<MusicTrack>,
+    pub sounds: Vec<SoundEffect>,
+    pub current: Option<usize>,
+    pub paused: bool,
+    pub looping: bool,
+    // how many passes of a track go into an exported WAV file
+    pub export_loops: u32,
+    // seconds played of the current pass
+    position: f64,
+    last_time: Option<f64>,
+}
+
+#[cfg(feature = "web")]
+impl Jukebox {
+    pub fn new(audio: GameAudio) -> Jukebox {
+        Jukebox {
+            tracks: audio.tracks,
+            sounds: audio.sounds,
+            looping: true,
+            export_loops: 1,
+            ..Default::default()
+        }
+    }
+
+    pub fn play(&mut self, ix: usize) {
+        let Some(track) = self.tracks.get(ix) else {
+            return;
+        };
+        audio::play_imf(&track.imf);
+        self.current = Some(ix);
+        self.paused = false;
+        self.position = 0.0;
+        self.last_time = None;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        if self.current.is_some() {
+            self.paused = !self.paused;
+            audio::pause_imf(self.paused);
+        }
+    }
+
+    pub fn stop(&mut self) {
+        if self.current.take().is_some() {
+            audio::stop_imf();
+            if self.paused {
+                audio::pause_imf(false);
+            }
+        }
+        self.paused = false;
+    }
+
+    /// Plays the track `delta` places away from the current one.
+    pub fn skip(&mut self, delta: isize) {
+        if self.tracks.is_empty() {
+            return;
+        }
+        let current = self.current.unwrap_or(0) as isize;
+        let ix = (current + delta).rem_euclid(self.tracks.len() as isize);
+        self.play(ix as usize);
+    }
+
+    pub fn position(&self) -> f64 {
+        self.position
+    }
+
+    /// Advances the play time to `time` (seconds, egui's input time) and
+    /// starts over or moves on at the end of a track. Long gaps between
+    /// the updates only count MAX_UPDATE_STEP.
+    pub fn update(&mut self, time: f64) {
+        let Some(current) = self.current else {
+            return;
+        };
+        if let Some(last_time) = self.last_time
+            && !self.paused
+        {
+            self.position += (time - last_time).clamp(0.0, MAX_UPDATE_STEP);
+        }
+        self.last_time = Some(time);
+
+        let duration = self.tracks[current].duration();
+        if duration > 0.0 && self.position >= duration {
+            if self.looping {
+                self.play(current);
+            } else {
+                self.skip(1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AUDIOHED: &[u8] = include_bytes!("../public/shareware/AUDIOHED.WL1");
+    const AUDIOT: &[u8] = include_bytes!("../public/shareware/AUDIOT.WL1");
+
+    // the music of the shareware, most chunks only keep their name
+    const SHAREWARE_TRACKS: [&str; 11] = [
+        "CORNER", "WARMARCH", "GETTHEM", "NAZI_NOR", "POW", "SEARCHN", "SUSPENSE", "WONDERIN",
+        "ENDLEVEL", "ROSTER", "URAHERO",
+    ];
+
+    #[test]
+    fn parses_the_shareware_audio() {
+        let audio = parse_audio(AUDIOHED, AUDIOT).unwrap();
+
+        let names: Vec<&str> = audio.tracks.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, SHAREWARE_TRACKS);
+        assert!(audio.tracks.iter().all(|t| t.duration() > 0.0));
+        assert_eq!(audio.sounds.len(), 87);
+        assert!(
+            audio
+                .sounds
+                .iter()
+                .all(|s| s.adl.len() >= ADL_HEADER_SIZE && !s.name.is_empty())
+        );
+    }
+
+    #[test]
+    fn finds_the_music_after_the_digitized_sounds() {
+        let chunks = audio_chunks(AUDIOHED, AUDIOT).unwrap();
+        assert_eq!(chunks.len(), 288);
+        // 87 sounds each as PC speaker, AdLib and digitized sound
+        assert_eq!(chunks[260], ID_MARKER);
+
+        let tracks = parse_music(&chunks, 261);
+        assert_eq!(tracks.len(), SHAREWARE_TRACKS.len());
+        assert_eq!(tracks[0].chunk, 261);
+        let sounds = parse_sound_effects(&chunks, 87);
+        assert_eq!(sounds.first().map(|s| s.chunk), Some(87));
+        assert_eq!(sounds.last().map(|s| s.chunk), Some(173));
+    }
+
+    #[test]
+    fn track_name_needs_a_printable_terminated_name() {
+        assert_eq!(track_name(b"CORNER\0rest"), Some("CORNER".to_string()));
+        assert_eq!(track_name(b"CORNER"), None);
+        assert_eq!(track_name(b"\0"), None);
+        assert_eq!(track_name(b"A B\0"), None);
+    }
+
+    #[test]
+    fn rejects_offsets_outside_of_audiot() {
+        assert!(audio_chunks(AUDIOHED, &AUDIOT[..AUDIOT.len() / 2]).is_err());
+    }
+}
@@ -0,0 +1,163 @@
+// Remapping of the player's input to the keys the engine understands.
+// Inputs and keys are named like the `key` of a browser KeyboardEvent
+// (what the forwarder dispatches), mouse buttons have names of their own.
+// Unbound keyboard keys are forwarded unchanged, unbound mouse buttons not.
+
+use serde::{Deserialize, Serialize};
+
+pub const MOUSE_PRIMARY: &str = "MousePrimary";
+pub const MOUSE_SECONDARY: &str = "MouseSecondary";
+#[cfg(feature = "web")]
+pub const MOUSE_MIDDLE: &str = "MouseMiddle";
+#[cfg(feature = "web")]
+pub const MOUSE_INPUTS: [&str; 3] = [MOUSE_PRIMARY, MOUSE_SECONDARY, MOUSE_MIDDLE];
+// egui does not report these as keys, keyboard.rs forwards them
+#[cfg(feature = "web")]
+pub const MODIFIER_KEYS: [&str; 3] = ["Control", "Alt", "Shift"];
+
+#[cfg(feature = "web")]
+/// The keys of the original game controls and what they do.
+pub const GAME_KEYS: [(&str, &str); 14] = [
+    ("ArrowUp", "Forward"),
+    ("ArrowDown", "Backward"),
+    ("ArrowLeft", "Turn left"),
+    ("ArrowRight", "Turn right"),
+    ("Control", "Fire"),
+    ("Alt", "Strafe"),
+    ("Shift", "Run"),
+    (" ", "Use"),
+    ("1", "Knife"),
+    ("2", "Pistol"),
+    ("3", "Machine gun"),
+    ("4", "Chain gun"),
+    ("Escape", "Menu"),
+    ("Enter", "Confirm"),
+];
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum KeyPreset {
+    Classic,
+    ModernWasd,
+    LeftHanded,
+    // edited by the player
+    Custom,
+}
+
+impl KeyPreset {
+    #[cfg(feature = "web")]
+    pub const ALL: [KeyPreset; 3] = [
+        KeyPreset::Classic,
+        KeyPreset::ModernWasd,
+        KeyPreset::LeftHanded,
+    ];
+
+    #[cfg(feature = "web")]
+    pub fn title(&self) -> &'static str {
+        match self {
+            KeyPreset::Classic => "Classic",
+            KeyPreset::ModernWasd => "Modern (WASD)",
+            KeyPreset::LeftHanded => "Left-handed",
+            KeyPreset::Custom => "Custom",
+        }
+    }
+
+    fn bindings(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            KeyPreset::Classic | KeyPreset::Custom => &[],
+            KeyPreset::ModernWasd => &[
+                ("W", "ArrowUp"),
+                ("S", "ArrowDown"),
+                ("A", "ArrowLeft"),
+                ("D", "ArrowRight"),
+                ("E", " "),
+                (MOUSE_PRIMARY, "Control"),
+                (MOUSE_SECONDARY, "Alt"),
+            ],
+            KeyPreset::LeftHanded => &[
+                ("I", "ArrowUp"),
+                ("K", "ArrowDown"),
+                ("J", "ArrowLeft"),
+                ("L", "ArrowRight"),
+                ("U", " "),
+                ("H", "Control"),
+                ("N", "Alt"),
+                (MOUSE_PRIMARY, "Control"),
+                (MOUSE_SECONDARY, "Alt"),
+            ],
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyBinding {
+    pub input: String,
+    pub key: String,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub preset: KeyPreset,
+    pub bindings: Vec<KeyBinding>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> KeyBindings {
+        KeyBindings::from_preset(KeyPreset::Classic)
+    }
+}
+
+impl KeyBindings {
+    pub fn from_preset(preset: KeyPreset) -> KeyBindings {
+        KeyBindings {
+            preset,
+            bindings: preset
+                .bindings()
+                .iter()
+                .map(|(input, key)| KeyBinding {
+                    input: input.to_string(),
+                    key: key.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[cfg(feature = "web")]
+    /// The key bound to the input, None if the input is not bound.
+    pub fn lookup(&self, input: &str) -> Option<&str> {
+        self.bindings
+            .iter()
+            .find(|binding| binding.input == input)
+            .map(|binding| binding.key.as_str())
+    }
+
+    /// The key to forward for a keyboard key.
+    #[cfg(feature = "web")]
+    pub fn map<'a>(&'a self, key: &'a str) -> &'a str {
+        self.lookup(key).unwrap_or(key)
+    }
+}
+
+#[cfg(feature = "web")]
+/// Display name of an input or key, the space has none.
+pub fn key_label(key: &str) -> &str {
+    match key {
+        " " => "Space",
+        key => key,
+    }
+}
+
+#[cfg(feature = "web")]
+/// The key events that turn the held keys into the new held keys,
+/// true for a key going down.
+pub fn key_changes(held: &[String], new_held: &[String]) -> Vec<(String, bool)> {
+    let released = held
+        .iter()
+        .filter(|key| !new_held.contains(key))
+        .map(|key| (key.clone(), false));
+    let pressed = new_held
+        .iter()
+        .filter(|key| !held.contains(key))
+        .map(|key| (key.clone(), true));
+    released.chain(pressed).collect()
+}
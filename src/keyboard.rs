@@ -0,0 +1,189 @@
+// All input reaches the engine as synthetic keyboard events on the `vga`
+// canvas. The keys held down that way are tracked, so they can all be
+// released when the window loses the focus (no stuck movement).
+//
+// egui does not report the modifier keys as keys, they are forwarded by
+// listening to the document directly, with their real held state.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use js_sys::Reflect;
+use wasm_bindgen::prelude::*;
+use web_sys::{HtmlElement, KeyboardEvent, window};
+
+use crate::key_bindings::{GAME_KEYS, KeyBindings, MODIFIER_KEYS};
+
+const KEYDOWN_EVENT: &str = "keydown";
+const KEYUP_EVENT: &str = "keyup";
+const BLUR_EVENT: &str = "blur";
+// the code of the M in the mute shortcut (Alt+M, see app.rs), by code as
+// Alt changes the key on some layouts
+const MUTE_CODE: &str = "KeyM";
+
+thread_local! {
+    static HELD_KEYS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+pub fn dispatch_key_event(key: &str, pressed: bool) {
+    HELD_KEYS.with_borrow_mut(|held| {
+        held.retain(|k| k != key);
+        if pressed {
+            held.push(key.to_string());
+        }
+    });
+
+    let init = web_sys::KeyboardEventInit::new();
+    init.set_key(key);
+    init.set_bubbles(true);
+    init.set_cancelable(true);
+
+    let event_type = if pressed { KEYDOWN_EVENT } else { KEYUP_EVENT };
+    let event = KeyboardEvent::new_with_keyboard_event_init_dict(event_type, &init).expect("event");
+
+    let vga = window()
+        .and_then(|w| w.document())
+        .and_then(|d| d.get_element_by_id("vga"))
+        .expect("Element not found");
+
+    vga.dispatch_event(&event).expect("event dispatch");
+}
+
+pub fn release_all_keys() {
+    let held = HELD_KEYS.take();
+    for key in held {
+        dispatch_key_event(&key, false);
+    }
+}
+
+/// Forwards Ctrl, Alt and Shift (mapped by the key bindings) for as long
+/// as they are held, suppresses the browser shortcuts on the keys of the
+/// game while it runs and releases all keys on blur.
+pub fn register_modifier_handler(key_bindings: Rc<RefCell<KeyBindings>>) {
+    let window = window().expect("No window object found");
+    let document = window.document().expect("No document object found");
+    // the forwarded key of each held modifier, the bindings may change meanwhile
+    let held_modifiers: Rc<RefCell<Vec<(String, String)>>> = Rc::new(RefCell::new(Vec::new()));
+
+    {
+        let held_modifiers = held_modifiers.clone();
+        let closure_keydown = Closure::wrap(Box::new(move |e: KeyboardEvent| {
+            // the synthetic events bubble up to the document as well
+            if !e.is_trusted() || !is_playing() {
+                return;
+            }
+            if is_mute_chord(&e) {
+                // the mute shortcut is not meant for the game, take its Alt back
+                e.prevent_default();
+                release_held(&mut held_modifiers.borrow_mut(), "Alt");
+                return;
+            }
+            if (e.ctrl_key() || e.alt_key()) && is_game_key(&key_bindings.borrow(), &e.key()) {
+                e.prevent_default(); // Ctrl+Arrow, Alt opening the menu bar, ...
+            }
+
+            let modifier = e.key();
+            if !MODIFIER_KEYS.contains(&modifier.as_str()) || e.repeat() {
+                return;
+            }
+            let key = key_bindings.borrow().map(&modifier).to_string();
+            dispatch_key_event(&key, true);
+            held_modifiers.borrow_mut().push((modifier, key));
+        }) as Box<dyn FnMut(_)>);
+        document
+            .add_event_listener_with_callback(
+                KEYDOWN_EVENT,
+                closure_keydown.as_ref().unchecked_ref(),
+            )
+            .expect("keydown listener");
+        closure_keydown.forget();
+    }
+
+    {
+        let held_modifiers = held_modifiers.clone();
+        let closure_keyup = Closure::wrap(Box::new(move |e: KeyboardEvent| {
+            if !e.is_trusted() {
+                return;
+            }
+            let modifier = e.key();
+            if MODIFIER_KEYS.contains(&modifier.as_str()) && is_playing() {
+                e.prevent_default();
+            }
+
+            release_held(&mut held_modifiers.borrow_mut(), &modifier);
+        }) as Box<dyn FnMut(_)>);
+        document
+            .add_event_listener_with_callback(KEYUP_EVENT, closure_keyup.as_ref().unchecked_ref())
+            .expect("keyup listener");
+        closure_keyup.forget();
+    }
+
+    {
+        let closure_blur = Closure::wrap(Box::new(move || {
+            held_modifiers.borrow_mut().clear();
+            release_all_keys();
+        }) as Box<dyn FnMut()>);
+        window
+            .add_event_listener_with_callback(BLUR_EVENT, closure_blur.as_ref().unchecked_ref())
+            .expect("blur listener");
+        closure_blur.forget();
+    }
+}
+
+/// Releases the key forwarded for a held modifier, if any.
+fn release_held(held: &mut Vec<(String, String)>, modifier: &str) {
+    if let Some(ix) = held.iter().position(|(m, _)| m == modifier) {
+        let (_, key) = held.remove(ix);
+        dispatch_key_event(&key, false);
+    }
+}
+
+/// Whether the key, after the bindings, is one the game uses. The browser
+/// keeps its shortcuts for all other keys, Ctrl+R still reloads.
+fn is_game_key(key_bindings: &KeyBindings, key: &str) -> bool {
+    // the bindings name letters in upper case, like egui does
+    let key = if key.chars().count() == 1 {
+        key.to_uppercase()
+    } else {
+        key.to_string()
+    };
+    let key = key_bindings.map(&key);
+    GAME_KEYS.iter().any(|(game_key, _)| *game_key == key)
+}
+
+fn is_mute_chord(e: &KeyboardEvent) -> bool {
+    e.code() == MUTE_CODE && e.alt_key() && !e.ctrl_key() && !e.shift_key() && !e.meta_key()
+}
+
+/// Marks the game as running for the listeners above (and the engine)
+/// and moves the keyboard focus to its canvas.
+pub fn start_playing() {
+    let window = window().expect("No window object found");
+    let document = window.document().expect("No document object found");
+
+    Reflect::set(&window, &JsValue::from_str("iw_playing"), &true.into())
+        .expect("mark iw as playing");
+
+    let element = document
+        .get_element_by_id("vga")
+        .expect("Element not found");
+    if let Some(html_element) = element.dyn_ref::<HtmlElement>() {
+        html_element.focus().expect("Failed to focus element");
+    }
+}
+
+/// The game did not start after all: the browser gets its keys back and
+/// the keys sent to the game so far are released.
+pub fn stop_playing() {
+    if let Some(window) = window() {
+        Reflect::set(&window, &JsValue::from_str("iw_playing"), &false.into())
+            .expect("mark iw as not playing");
+    }
+    release_all_keys();
+}
+
+fn is_playing() -> bool {
+    window()
+        .and_then(|w| Reflect::get(&w, &JsValue::from_str("iw_playing")).ok())
+        .is_some_and(|playing| playing.is_truthy())
+}
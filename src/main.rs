@@ -1,24 +1,95 @@
 mod app;
+mod audio;
+mod config_file;
+#[cfg(feature = "web")]
+mod crash;
+mod engine_config;
+mod gamepad;
+#[cfg(any(feature = "web", test))]
+mod jukebox;
+mod key_bindings;
+#[cfg(feature = "web")]
+mod keyboard;
+mod mouse_look;
+#[cfg(all(feature = "web", feature = "debug"))]
+mod opl_debug;
+mod release;
+mod savegame;
+mod settings;
+#[cfg(all(not(feature = "web"), any(feature = "desktop", test)))]
+mod shareware;
+mod storage;
+mod task;
+mod touch;
+mod validate;
+mod version;
+#[cfg(feature = "web")]
+mod wav_export;
 
 use app::IWApp;
+use storage::PlatformStorage;
 
-#[cfg(not(feature = "web"))]
-fn main() -> eframe::Result {
-    use crate::app::load_upload_state;
+#[cfg(all(feature = "web", feature = "desktop"))]
+compile_error!("the web and desktop features pick different engine backends");
 
-    let native_options = eframe::NativeOptions {
+#[cfg(not(feature = "web"))]
+fn native_options() -> eframe::NativeOptions {
+    eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([400.0, 300.0])
+            .with_inner_size([800.0, 600.0])
             .with_min_inner_size([300.0, 220.0]),
         ..Default::default()
-    };
+    }
+}
+
+#[cfg(feature = "desktop")]
+fn main() -> eframe::Result {
+    use std::cell::RefCell;
+    use std::rc::Rc;
 
-    let _upload_state = load_upload_state();
+    use crate::app::{NativeLaunch, load_settings, load_upload_state};
 
+    let storage = PlatformStorage::default();
+    let launch = Rc::new(RefCell::new(NativeLaunch::default()));
+    // the engine needs the main thread: the player window closes for a
+    // game and opens again when it ends
+    loop {
+        let upload_state = pollster::block_on(load_upload_state(&storage));
+        let settings = pollster::block_on(load_settings(&storage));
+        let app_storage = storage.clone();
+        let app_launch = launch.clone();
+        eframe::run_native(
+            "Iron-Wolf",
+            native_options(),
+            Box::new(|cc| {
+                Ok(Box::new(IWApp::new(
+                    cc,
+                    app_storage,
+                    upload_state,
+                    settings,
+                    app_launch,
+                )))
+            }),
+        )?;
+        if !launch.borrow_mut().run_game() {
+            return Ok(());
+        }
+    }
+}
+
+/// Without the engine (neither the web nor the desktop feature) only the
+/// player itself runs, to manage installations, settings and save games.
+#[cfg(not(any(feature = "web", feature = "desktop")))]
+fn main() -> eframe::Result {
+    use crate::app::{load_settings, load_upload_state};
+
+    let storage = PlatformStorage::default();
+    let upload_state = pollster::block_on(load_upload_state(&storage));
+    let settings = pollster::block_on(load_settings(&storage));
     eframe::run_native(
-        "eframe template",
-        native_options,
-        Box::new(|cc| Ok(Box::new(IWApp::new(cc, app::UploadState::no_upload())))),
+        "Iron-Wolf",
+        native_options(),
+        Box::new(|cc| Ok(Box::new(IWApp::new(cc, storage, upload_state, settings)))),
     )
 }
 
@@ -28,11 +99,12 @@ fn main() {
 
     // Redirect `log` message to `console.log` and friends:
     eframe::WebLogger::init(log::LevelFilter::Debug).ok();
+    crash::install_panic_hook();
 
     let web_options = eframe::WebOptions::default();
 
     wasm_bindgen_futures::spawn_local(async {
-        use crate::app::load_upload_state;
+        use crate::app::{load_settings, load_upload_state};
 
         let document = web_sys::window()
             .expect("No window")
@@ -45,16 +117,20 @@ fn main() {
             .dyn_into::<web_sys::HtmlCanvasElement>()
             .expect("iw_player_canvas was not a HtmlCanvasElement");
 
-        let upload_state = load_upload_state().await;
+        let storage = PlatformStorage::default();
+        let upload_state = load_upload_state(&storage).await;
+        let settings = load_settings(&storage).await;
 
         let start_result = eframe::WebRunner::new()
             .start(
                 canvas,
                 web_options,
-                Box::new(|cc| Ok(Box::new(IWApp::new(cc, upload_state)))),
+                Box::new(|cc| Ok(Box::new(IWApp::new(cc, storage, upload_state, settings)))),
             )
             .await;
 
+        register_service_worker();
+
         // Remove the loading text and spinner:
         if let Some(loading_text) = document.get_element_by_id("loading_text") {
             match start_result {
@@ -71,3 +147,26 @@ fn main() {
         }
     });
 }
+
+/// Registers the service worker for offline play. The crate version is
+/// passed along, the worker names its caches after it.
+#[cfg(feature = "web")]
+fn register_service_worker() {
+    let window = web_sys::window().expect("No window");
+    if window.location().hash().is_ok_and(|hash| hash == "#dev") {
+        return;
+    }
+    let navigator = window.navigator();
+    // not available in insecure contexts (plain http)
+    if !js_sys::Reflect::has(&navigator, &"serviceWorker".into()).unwrap_or(false) {
+        return;
+    }
+
+    let url = format!("sw.js?version={}", env!("CARGO_PKG_VERSION"));
+    let registration = navigator.service_worker().register(&url);
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Err(e) = wasm_bindgen_futures::JsFuture::from(registration).await {
+            log::warn!("service worker registration failed: {:?}", e);
+        }
+    });
+}
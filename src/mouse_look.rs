@@ -0,0 +1,159 @@
+// Mouse look with the pointer locked to the player canvas. The engine
+// only understands keys, so the relative mouse movement is turned into
+// holding the turn (and move) keys for as long as the movement lasts.
+
+#[cfg(feature = "web")]
+use std::cell::Cell;
+#[cfg(feature = "web")]
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "web")]
+use wasm_bindgen::prelude::*;
+#[cfg(feature = "web")]
+use web_sys::{MouseEvent, window};
+
+#[cfg(feature = "web")]
+use crate::key_bindings::{MOUSE_PRIMARY, MOUSE_SECONDARY, key_changes};
+
+#[cfg(feature = "web")]
+pub const PLAYER_CANVAS_ID: &str = "iw_player_canvas";
+// how many pixels of mouse movement one second of holding a key is worth
+#[cfg(feature = "web")]
+const PIXELS_PER_SECOND: f32 = 600.0;
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MouseLookSettings {
+    pub enabled: bool,
+    pub sensitivity: f32,
+    // moving the mouse forward/backward moves, like in the original game
+    pub vertical_move: bool,
+    pub invert_y: bool,
+}
+
+impl Default for MouseLookSettings {
+    fn default() -> MouseLookSettings {
+        MouseLookSettings {
+            enabled: false,
+            sensitivity: 1.0,
+            vertical_move: true,
+            invert_y: false,
+        }
+    }
+}
+
+#[cfg(feature = "web")]
+pub struct MouseLook {
+    // movement reported by the mousemove listener since the last frame
+    moved: Rc<Cell<(f32, f32)>>,
+    // movement not yet turned into held keys
+    pending: (f32, f32),
+    held: Vec<String>,
+}
+
+#[cfg(feature = "web")]
+impl MouseLook {
+    /// Starts listening to mouse movement, there should only be one.
+    pub fn install() -> MouseLook {
+        let moved = Rc::new(Cell::new((0.0, 0.0)));
+        register_mouse_move_handler(moved.clone());
+        MouseLook {
+            moved,
+            pending: (0.0, 0.0),
+            held: Vec::new(),
+        }
+    }
+
+    pub fn is_locked(&self) -> bool {
+        locked_document().is_some()
+    }
+
+    pub fn lock() {
+        let canvas = window()
+            .and_then(|w| w.document())
+            .and_then(|d| d.get_element_by_id(PLAYER_CANVAS_ID));
+        if let Some(canvas) = canvas {
+            canvas.request_pointer_lock();
+        }
+    }
+
+    pub fn unlock() {
+        if let Some(document) = locked_document() {
+            document.exit_pointer_lock();
+        }
+    }
+
+    /// Turns the mouse movement into held keys. `dt` is the frame time
+    /// in seconds. Returns the keys that went down (true) or up (false)
+    /// and whether movement is still pending (needs another frame).
+    pub fn update(&mut self, dt: f32, settings: &MouseLookSettings) -> (Vec<(String, bool)>, bool) {
+        let (dx, dy) = self.moved.take();
+        if !settings.enabled || !self.is_locked() {
+            self.pending = (0.0, 0.0);
+            let changes = key_changes(&self.held, &[]);
+            self.held.clear();
+            return (changes, false);
+        }
+
+        let dy = if settings.invert_y { -dy } else { dy };
+        self.pending.0 += dx * settings.sensitivity;
+        if settings.vertical_move {
+            self.pending.1 += dy * settings.sensitivity;
+        }
+
+        let mut held = Vec::new();
+        let budget = dt * PIXELS_PER_SECOND;
+        for (pending, negative, positive) in [
+            (&mut self.pending.0, "ArrowLeft", "ArrowRight"),
+            (&mut self.pending.1, "ArrowUp", "ArrowDown"),
+        ] {
+            if pending.abs() < 1.0 {
+                *pending = 0.0;
+                continue;
+            }
+            let key = if *pending < 0.0 { negative } else { positive };
+            held.push(key.to_string());
+            *pending -= pending.signum() * budget.min(pending.abs());
+        }
+
+        let changes = key_changes(&self.held, &held);
+        self.held = held;
+        let pending = self.pending != (0.0, 0.0);
+        (changes, pending)
+    }
+}
+
+#[cfg(feature = "web")]
+/// The key mouse buttons fire and strafe with while the pointer is
+/// locked, if the key bindings do not say otherwise.
+pub fn default_button_key(input: &str) -> Option<&'static str> {
+    match input {
+        MOUSE_PRIMARY => Some("Control"),
+        MOUSE_SECONDARY => Some("Alt"),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "web")]
+fn locked_document() -> Option<web_sys::Document> {
+    let document = window()?.document()?;
+    let locked = document.pointer_lock_element()?;
+    (locked.id() == PLAYER_CANVAS_ID).then_some(document)
+}
+
+#[cfg(feature = "web")]
+fn register_mouse_move_handler(moved: Rc<Cell<(f32, f32)>>) {
+    let closure = Closure::wrap(Box::new(move |e: MouseEvent| {
+        if locked_document().is_some() {
+            let (x, y) = moved.get();
+            moved.set((x + e.movement_x() as f32, y + e.movement_y() as f32));
+        }
+    }) as Box<dyn FnMut(_)>);
+    if let Some(document) = window().and_then(|w| w.document()) {
+        document
+            .add_event_listener_with_callback("mousemove", closure.as_ref().unchecked_ref())
+            .expect("mousemove listener");
+    }
+    closure.forget();
+}
@@ -0,0 +1,333 @@
+// Register level view of the OPL2 chip, only built for the web with the
+// `debug` feature. On request the worklet (public/oplDebug.js, loaded
+// next to public/oplProcessor.js) keeps a shadow of the registers and
+// posts it back over its port about 20 times a second, together with the
+// last generated block and the writes since.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use egui::{Color32, Pos2, RichText, Sense, Stroke, Vec2};
+use js_sys::{Array, Float32Array, Object, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+use web_sys::{AudioWorkletNode, MessageEvent, window};
+
+use crate::audio::opl_node;
+
+// index.html loads the debug part of the worklet if it is set
+const OPL_DEBUG_KEY: &str = "iw_opl_debug";
+const DEBUG_MESSAGE_TYPE: &str = "opl_debug";
+const MAX_WRITES: usize = 200;
+const CHANNELS: usize = 9;
+// offset of the modulator operator of each channel, the carrier is 3 after it
+const OPERATOR_OFFSETS: [u8; CHANNELS] = [0, 1, 2, 8, 9, 10, 16, 17, 18];
+// the input clock of the chip divided by 72
+const OPL_SAMPLE_RATE: f64 = 49716.0;
+const SOURCES: [&str; 3] = ["engine", "music", "sfx"];
+const SCOPE_STROKE_WIDTH: f32 = 1.0;
+
+#[derive(Default)]
+struct DebugState {
+    regs: Vec<u8>,
+    // mono samples of the last block
+    scope: Vec<f32>,
+    // (register, value, source), the newest last
+    writes: VecDeque<(u8, u8, u8)>,
+}
+
+struct Channel {
+    key_on: bool,
+    block: u8,
+    fnum: u16,
+    // the 20, 40, 60, 80 and E0 registers of modulator and carrier
+    modulator: [u8; 5],
+    carrier: [u8; 5],
+    // feedback and connection
+    c0: u8,
+}
+
+impl Channel {
+    fn from_regs(regs: &[u8], ch: usize) -> Channel {
+        let reg = |r: usize| regs.get(r).copied().unwrap_or(0);
+        let operator =
+            |offset: usize| [0x20, 0x40, 0x60, 0x80, 0xE0].map(|base: usize| reg(base + offset));
+        let b0 = reg(0xB0 + ch);
+        let op = OPERATOR_OFFSETS[ch] as usize;
+        Channel {
+            key_on: b0 & 0x20 != 0,
+            block: (b0 >> 2) & 7,
+            fnum: reg(0xA0 + ch) as u16 | ((b0 as u16 & 3) << 8),
+            modulator: operator(op),
+            carrier: operator(op + 3),
+            c0: reg(0xC0 + ch),
+        }
+    }
+
+    fn frequency(&self) -> f64 {
+        self.fnum as f64 * OPL_SAMPLE_RATE / (1u32 << (20 - self.block)) as f64
+    }
+}
+
+#[derive(Default)]
+pub struct OplDebug {
+    pub open: bool,
+    // the node debugging was enabled on, the engine creates a new one
+    // with every start
+    node: Option<AudioWorkletNode>,
+    listener: Option<Closure<dyn FnMut(MessageEvent)>>,
+    state: Rc<RefCell<DebugState>>,
+}
+
+impl OplDebug {
+    /// Has the engine load the debug part of the worklet, it has to be
+    /// installed before the engine starts.
+    pub fn install() -> OplDebug {
+        if let Some(window) = window()
+            && let Err(e) = Reflect::set(&window, &OPL_DEBUG_KEY.into(), &JsValue::TRUE)
+        {
+            log::warn!("enabling the OPL debug module failed: {:?}", e);
+        }
+        OplDebug::default()
+    }
+
+    /// Turns the feedback of the worklet on or off with the window.
+    pub fn update(&mut self) {
+        let node = opl_node().filter(|_| self.open);
+        let same_node = match (&self.node, &node) {
+            (Some(a), Some(b)) => Object::is(a, b),
+            (None, None) => true,
+            _ => false,
+        };
+        if same_node {
+            return;
+        }
+        if let Some(old) = self.node.take() {
+            self.detach(&old);
+        }
+        if let Some(node) = node {
+            match self.attach(&node) {
+                Ok(()) => self.node = Some(node),
+                Err(e) => log::warn!("enabling the OPL debug feedback failed: {:?}", e),
+            }
+        }
+    }
+
+    fn attach(&mut self, node: &AudioWorkletNode) -> Result<(), JsValue> {
+        let state = self.state.clone();
+        let listener = Closure::wrap(Box::new(move |e: MessageEvent| {
+            // the engine gets these messages as well, it only looks for "adl_finished"
+            let data = e.data();
+            let is_debug = Reflect::get(&data, &"type".into())
+                .is_ok_and(|t| t.as_string().as_deref() == Some(DEBUG_MESSAGE_TYPE));
+            if is_debug {
+                state.borrow_mut().receive(&data);
+            }
+        }) as Box<dyn FnMut(_)>);
+        let port = node.port()?;
+        port.add_event_listener_with_callback("message", listener.as_ref().unchecked_ref())?;
+        port.start();
+        post_debug(node, true)?;
+        self.listener = Some(listener);
+        Ok(())
+    }
+
+    fn detach(&mut self, node: &AudioWorkletNode) {
+        let _ = post_debug(node, false);
+        if let (Some(listener), Ok(port)) = (self.listener.take(), node.port()) {
+            let _ = port
+                .remove_event_listener_with_callback("message", listener.as_ref().unchecked_ref());
+        }
+        *self.state.borrow_mut() = DebugState::default();
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        if !self.open {
+            return;
+        }
+        let mut open = self.open;
+        let state = self.state.borrow();
+        egui::Window::new("OPL debug")
+            .open(&mut open)
+            .default_width(520.0)
+            .show(ui, |ui| {
+                if self.node.is_none() {
+                    ui.label("The game has not set up its sound yet.");
+                    return;
+                }
+                channel_table(ui, &state.regs);
+                ui.separator();
+                ui.horizontal(|ui| {
+                    scope_plot(ui, &state.scope);
+                    spectrum_plot(ui, &state.scope);
+                });
+                ui.separator();
+                write_log(ui, &state.writes);
+            });
+        drop(state);
+        self.open = open;
+        if self.open {
+            ui.ctx().request_repaint(); // the worklet keeps sending
+        }
+    }
+}
+
+impl DebugState {
+    fn receive(&mut self, data: &JsValue) {
+        let field = |name: &str| Reflect::get(data, &name.into()).unwrap_or(JsValue::UNDEFINED);
+        self.regs = Uint8Array::new(&field("regs")).to_vec();
+        self.scope = Float32Array::new(&field("scope")).to_vec();
+        let writes: Array = field("writes").dyn_into().unwrap_or_default();
+        for write in writes.iter() {
+            let part = |ix: u32| Reflect::get_u32(&write, ix).ok().and_then(|v| v.as_f64());
+            if let (Some(reg), Some(value), Some(source)) = (part(0), part(1), part(2)) {
+                self.writes
+                    .push_back((reg as u8, value as u8, source as u8));
+            }
+        }
+        while self.writes.len() > MAX_WRITES {
+            self.writes.pop_front();
+        }
+    }
+}
+
+fn post_debug(node: &AudioWorkletNode, enabled: bool) -> Result<(), JsValue> {
+    let message = Object::new();
+    Reflect::set(&message, &"cmd".into(), &"debug".into())?;
+    Reflect::set(&message, &"enabled".into(), &enabled.into())?;
+    node.port()?.post_message(&message)
+}
+
+fn channel_table(ui: &mut egui::Ui, regs: &[u8]) {
+    egui::Grid::new("opl_channels")
+        .striped(true)
+        .num_columns(6)
+        .show(ui, |ui| {
+            for header in ["Ch", "Key", "Block/F-Num", "Hz", "Modulator", "Carrier"] {
+                ui.label(RichText::new(header).strong());
+            }
+            ui.end_row();
+            for ch in 0..CHANNELS {
+                let channel = Channel::from_regs(regs, ch);
+                ui.monospace(ch.to_string());
+                let (key, colour) = if channel.key_on {
+                    ("on", Color32::LIGHT_GREEN)
+                } else {
+                    ("off", Color32::GRAY)
+                };
+                ui.label(RichText::new(key).monospace().color(colour));
+                ui.monospace(format!("{} / {:4}", channel.block, channel.fnum));
+                ui.monospace(format!("{:7.1}", channel.frequency()));
+                ui.monospace(hex_bytes(&channel.modulator));
+                ui.monospace(format!(
+                    "{} C0:{:02X}",
+                    hex_bytes(&channel.carrier),
+                    channel.c0
+                ));
+                ui.end_row();
+            }
+        });
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn plot_area(ui: &mut egui::Ui, label: &str) -> (egui::Painter, egui::Rect) {
+    ui.vertical(|ui| {
+        ui.label(label);
+        let (response, painter) = ui.allocate_painter(Vec2::new(250.0, 100.0), Sense::hover());
+        painter.rect_filled(response.rect, 0.0, Color32::BLACK);
+        (painter, response.rect)
+    })
+    .inner
+}
+
+fn scope_plot(ui: &mut egui::Ui, samples: &[f32]) {
+    let (painter, rect) = plot_area(ui, "Scope");
+    if samples.len() < 2 {
+        return;
+    }
+    let step = rect.width() / (samples.len() - 1) as f32;
+    let points: Vec<Pos2> = samples
+        .iter()
+        .enumerate()
+        .map(|(ix, s)| {
+            let y = rect.center().y - s.clamp(-1.0, 1.0) * rect.height() / 2.0;
+            Pos2::new(rect.left() + ix as f32 * step, y)
+        })
+        .collect();
+    painter.add(egui::Shape::line(
+        points,
+        Stroke::new(SCOPE_STROKE_WIDTH, Color32::LIGHT_GREEN),
+    ));
+}
+
+fn spectrum_plot(ui: &mut egui::Ui, samples: &[f32]) {
+    let (painter, rect) = plot_area(ui, "Spectrum");
+    let bins = spectrum(samples);
+    if bins.is_empty() {
+        return;
+    }
+    let width = rect.width() / bins.len() as f32;
+    for (ix, magnitude) in bins.iter().enumerate() {
+        // -60 dB at the bottom
+        let db = 20.0 * magnitude.max(1e-6).log10();
+        let height = ((db + 60.0) / 60.0).clamp(0.0, 1.0) * rect.height();
+        let left = rect.left() + ix as f32 * width;
+        painter.rect_filled(
+            egui::Rect::from_min_max(
+                Pos2::new(left, rect.bottom() - height),
+                Pos2::new(left + width.max(1.0), rect.bottom()),
+            ),
+            0.0,
+            Color32::LIGHT_BLUE,
+        );
+    }
+}
+
+/// Magnitudes of the lower half of the DFT of the Hann windowed samples,
+/// a block is only 128 samples.
+fn spectrum(samples: &[f32]) -> Vec<f32> {
+    let n = samples.len();
+    if n < 2 {
+        return Vec::new();
+    }
+    let windowed: Vec<f32> = samples
+        .iter()
+        .enumerate()
+        .map(|(ix, s)| {
+            let w = 0.5 - 0.5 * (std::f32::consts::TAU * ix as f32 / (n - 1) as f32).cos();
+            s * w
+        })
+        .collect();
+    (0..n / 2)
+        .map(|k| {
+            let (mut re, mut im) = (0.0, 0.0);
+            for (ix, s) in windowed.iter().enumerate() {
+                let angle = std::f32::consts::TAU * (k * ix) as f32 / n as f32;
+                re += s * angle.cos();
+                im -= s * angle.sin();
+            }
+            // the Hann window halves the amplitude
+            4.0 * (re * re + im * im).sqrt() / n as f32
+        })
+        .collect()
+}
+
+fn write_log(ui: &mut egui::Ui, writes: &VecDeque<(u8, u8, u8)>) {
+    ui.label(format!("Register writes (last {})", MAX_WRITES));
+    egui::ScrollArea::vertical()
+        .max_height(150.0)
+        .stick_to_bottom(true)
+        .show(ui, |ui| {
+            for (reg, value, source) in writes {
+                let source = SOURCES.get(*source as usize).unwrap_or(&"?");
+                ui.monospace(format!("{:02X} <- {:02X}  {}", reg, value, source));
+            }
+        });
+}
@@ -0,0 +1,123 @@
+// Identifies the release an uploaded data set belongs to by the size and
+// CRC32 of its files. Only the shareware has verified checksums so far,
+// every other data set is reported as unknown. CONFIG files are never
+// part of a fingerprint, they change as soon as the game is played.
+
+pub struct Release {
+    pub name: &'static str,
+    pub files: &'static [KnownFile],
+}
+
+pub struct KnownFile {
+    pub name: &'static str,
+    pub size: usize,
+    pub crc32: u32,
+}
+
+/// Releases with verified checksums. New releases are added by running
+/// a CRC32 over each data file of an untouched installation.
+pub const KNOWN_RELEASES: &[Release] = &[Release {
+    name: "Shareware 1.4 (Apogee)",
+    files: &[
+        KnownFile {
+            name: "AUDIOHED.WL1",
+            size: 1156,
+            crc32: 0x52133DC4,
+        },
+        KnownFile {
+            name: "AUDIOT.WL1",
+            size: 132613,
+            crc32: 0x87BFC399,
+        },
+        KnownFile {
+            name: "GAMEMAPS.WL1",
+            size: 27425,
+            crc32: 0xCC53D341,
+        },
+        KnownFile {
+            name: "MAPHEAD.WL1",
+            size: 402,
+            crc32: 0x088F7551,
+        },
+        KnownFile {
+            name: "VGADICT.WL1",
+            size: 1024,
+            crc32: 0x358AB877,
+        },
+        KnownFile {
+            name: "VGAGRAPH.WL1",
+            size: 326568,
+            crc32: 0x746DA9F0,
+        },
+        KnownFile {
+            name: "VGAHEAD.WL1",
+            size: 471,
+            crc32: 0xA60A98F0,
+        },
+        KnownFile {
+            name: "VSWAP.WL1",
+            size: 742912,
+            crc32: 0x85EFAC55,
+        },
+    ],
+}];
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Fingerprint {
+    Unknown,
+    Release(&'static str),
+}
+
+/// Fingerprints a data set given as (file name, data) pairs.
+/// A release is detected if every file has the checksum of that release.
+pub fn fingerprint(files: &[(String, Vec<u8>)]) -> Fingerprint {
+    if files.is_empty() {
+        return Fingerprint::Unknown;
+    }
+    KNOWN_RELEASES
+        .iter()
+        .find(|release| {
+            files
+                .iter()
+                .all(|(name, data)| is_release_file(release, name, data))
+        })
+        .map_or(Fingerprint::Unknown, |release| {
+            Fingerprint::Release(release.name)
+        })
+}
+
+fn is_release_file(release: &Release, name: &str, data: &[u8]) -> bool {
+    release.files.iter().any(|file| {
+        // only hash files with a matching size
+        file.name == name && file.size == data.len() && file.crc32 == crc32fast::hash(data)
+    })
+}
+
+// the shareware is not compiled into the browser build
+#[cfg(all(test, not(feature = "web")))]
+mod tests {
+    use super::*;
+    use crate::shareware::shareware_files;
+
+    fn shareware_data_files() -> Vec<(String, Vec<u8>)> {
+        shareware_files()
+            .filter(|(name, _)| !name.starts_with("CONFIG."))
+            .map(|(name, data)| (name, data.to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn detects_the_shareware() {
+        assert_eq!(
+            fingerprint(&shareware_data_files()),
+            Fingerprint::Release("Shareware 1.4 (Apogee)")
+        );
+    }
+
+    #[test]
+    fn an_unknown_file_makes_the_release_unknown() {
+        let mut files = shareware_data_files();
+        files[0].1[0] ^= 0xFF;
+        assert_eq!(fingerprint(&files), Fingerprint::Unknown);
+    }
+}
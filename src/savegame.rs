@@ -0,0 +1,186 @@
+use std::io::{Cursor, Write};
+
+use crate::version::GameVersion;
+
+const SAVEGAME_PREFIX: &str = "SAVEGAM";
+// the first 32 bytes of a save game hold the description
+// the player typed in the save menu (zero terminated)
+const SAVEGAME_DESCRIPTION_LEN: usize = 32;
+const SAVEGAME_ZIP_NAME: &str = "iron-wolf-savegames.zip";
+// how long the blob url of a download stays valid
+#[cfg(feature = "web")]
+const BLOB_URL_LIFETIME_MS: i32 = 10_000;
+
+pub struct SaveGame {
+    pub file_name: String,
+    pub slot: usize,
+    pub description: String,
+    pub data: Vec<u8>,
+}
+
+impl SaveGame {
+    pub fn new(file_name: String, data: Vec<u8>) -> Option<SaveGame> {
+        let slot = savegame_slot(&file_name)?;
+        let description = savegame_description(&data);
+        Some(SaveGame {
+            file_name,
+            slot,
+            description,
+            data,
+        })
+    }
+}
+
+/// Returns the slot of a save game file name of the form SAVEGAM?.<ext>
+/// (ext being one of the GameVersion extensions) or None if the name
+/// is not a save game.
+pub fn savegame_slot(file_name: &str) -> Option<usize> {
+    let rest = file_name.strip_prefix(SAVEGAME_PREFIX)?;
+    let mut chars = rest.chars();
+    let slot = chars.next()?.to_digit(10)?;
+    let ext = chars.as_str().strip_prefix('.')?;
+    GameVersion::from_extension(ext)?;
+    Some(slot as usize)
+}
+
+/// Normalises an uploaded file name (e.g. savegam3.wl6) to the
+/// name the engine expects in its store.
+pub fn normalise_savegame_name(file_name: &str) -> Option<String> {
+    let upper = file_name.to_ascii_uppercase();
+    savegame_slot(&upper).map(|_| upper)
+}
+
+fn savegame_description(data: &[u8]) -> String {
+    let header = &data[..data.len().min(SAVEGAME_DESCRIPTION_LEN)];
+    let end = header.iter().position(|b| *b == 0).unwrap_or(header.len());
+    String::from_utf8_lossy(&header[..end]).trim().to_string()
+}
+
+pub fn savegames_zip(savegames: &[SaveGame]) -> Result<Vec<u8>, String> {
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default();
+    for savegame in savegames {
+        zip.start_file(savegame.file_name.as_str(), options)
+            .map_err(|e| e.to_string())?;
+        zip.write_all(&savegame.data).map_err(|e| e.to_string())?;
+    }
+    let cursor = zip.finish().map_err(|e| e.to_string())?;
+    Ok(cursor.into_inner())
+}
+
+pub fn download_savegames_zip(savegames: &[SaveGame]) -> Result<(), String> {
+    let data = savegames_zip(savegames)?;
+    download_file(SAVEGAME_ZIP_NAME, &data)
+}
+
+/// Offers the data as a file download to the user by clicking
+/// a temporary link to a blob url.
+#[cfg(feature = "web")]
+pub fn download_file(file_name: &str, data: &[u8]) -> Result<(), String> {
+    blob_download(file_name, data).map_err(|e| format!("{:?}", e))
+}
+
+/// Lets the user choose where to save the file.
+#[cfg(not(feature = "web"))]
+pub fn download_file(file_name: &str, data: &[u8]) -> Result<(), String> {
+    let Some(path) = rfd::FileDialog::new().set_file_name(file_name).save_file() else {
+        return Ok(()); // cancelled
+    };
+    std::fs::write(&path, data).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+#[cfg(feature = "web")]
+fn blob_download(file_name: &str, data: &[u8]) -> Result<(), wasm_bindgen::JsValue> {
+    use js_sys::{Array, Uint8Array};
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen::closure::Closure;
+    use web_sys::{Blob, BlobPropertyBag, HtmlAnchorElement, Url, window};
+
+    let window = window().ok_or("No window")?;
+    let document = window.document().ok_or("No document")?;
+
+    let parts = Array::new();
+    parts.push(&Uint8Array::from(data));
+    let options = BlobPropertyBag::new();
+    options.set_type("application/octet-stream");
+    let blob = Blob::new_with_u8_array_sequence_and_options(&parts, &options)?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+
+    let anchor = document
+        .create_element("a")?
+        .dyn_into::<HtmlAnchorElement>()?;
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+
+    // revoked right away some browsers cancel the download it just started
+    let revoke = Closure::once_into_js(move || {
+        let _ = Url::revoke_object_url(&url);
+    });
+    window.set_timeout_with_callback_and_timeout_and_arguments_0(
+        revoke.unchecked_ref(),
+        BLOB_URL_LIFETIME_MS,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn savegame_data(description: &[u8]) -> Vec<u8> {
+        let mut data = vec![0; SAVEGAME_DESCRIPTION_LEN + 16];
+        data[..description.len()].copy_from_slice(description);
+        data
+    }
+
+    #[test]
+    fn savegame_slot_needs_a_digit_and_a_known_extension() {
+        assert_eq!(savegame_slot("SAVEGAM0.WL6"), Some(0));
+        assert_eq!(savegame_slot("SAVEGAM9.SOD"), Some(9));
+        assert_eq!(savegame_slot("SAVEGAM1.WL9"), None);
+        assert_eq!(savegame_slot("SAVEGAMX.WL6"), None);
+        assert_eq!(savegame_slot("SAVEGAM1WL6"), None);
+        assert_eq!(savegame_slot("savegam1.wl6"), None);
+        assert_eq!(savegame_slot("CONFIG.WL6"), None);
+    }
+
+    #[test]
+    fn normalise_savegame_name_upper_cases_the_name() {
+        assert_eq!(
+            normalise_savegame_name("savegam3.wl6"),
+            Some("SAVEGAM3.WL6".to_string())
+        );
+        assert_eq!(normalise_savegame_name("savegame.wl6"), None);
+    }
+
+    #[test]
+    fn reads_the_description_up_to_the_terminator() {
+        let savegame =
+            SaveGame::new("SAVEGAM2.WL1".to_string(), savegame_data(b"E1M2 \0junk")).unwrap();
+        assert_eq!(savegame.slot, 2);
+        assert_eq!(savegame.description, "E1M2");
+
+        // no terminator within the description, a short file
+        assert_eq!(savegame_description(&[b'A'; 40]), "A".repeat(32));
+        assert_eq!(savegame_description(b"AB"), "AB");
+        assert!(SaveGame::new("CONFIG.WL1".to_string(), savegame_data(b"")).is_none());
+    }
+
+    #[test]
+    fn zips_all_savegames() {
+        let savegames: Vec<SaveGame> = ["SAVEGAM0.WL6", "SAVEGAM5.WL6"]
+            .iter()
+            .map(|name| SaveGame::new(name.to_string(), savegame_data(name.as_bytes())).unwrap())
+            .collect();
+
+        let zip = savegames_zip(&savegames).unwrap();
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(zip)).unwrap();
+        assert_eq!(archive.len(), 2);
+        let mut data = Vec::new();
+        std::io::Read::read_to_end(&mut archive.by_name("SAVEGAM5.WL6").unwrap(), &mut data)
+            .unwrap();
+        assert_eq!(data, savegames[1].data);
+    }
+}
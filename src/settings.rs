@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+
+use crate::audio::AudioSettings;
+use crate::engine_config::EngineSettings;
+use crate::gamepad::GamepadSettings;
+use crate::key_bindings::KeyBindings;
+use crate::mouse_look::MouseLookSettings;
+use crate::touch::TouchSettings;
+
+/// Bump this if a setting changes its meaning and add a migration step
+/// to `Settings::migrate`. Added settings only need a default.
+pub const SETTINGS_SCHEMA_VERSION: u32 = 1;
+
+// Missing fields fall back to their default, unknown fields are ignored.
+// That way settings stored by older or newer players can always be read.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub schema_version: u32,
+    pub show_frame_rate: bool,
+    pub engine_config: EngineSettings,
+    pub key_bindings: KeyBindings,
+    pub gamepad: GamepadSettings,
+    pub touch: TouchSettings,
+    pub mouse_look: MouseLookSettings,
+    pub audio: AudioSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            schema_version: SETTINGS_SCHEMA_VERSION,
+            show_frame_rate: false,
+            engine_config: EngineSettings::default(),
+            key_bindings: KeyBindings::default(),
+            gamepad: GamepadSettings::default(),
+            touch: TouchSettings::default(),
+            mouse_look: MouseLookSettings::default(),
+            audio: AudioSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn from_json(json: &str) -> Settings {
+        match serde_json::from_str::<Settings>(json) {
+            Ok(settings) => settings.migrate(),
+            Err(e) => {
+                log::warn!("stored settings unreadable, using defaults: {}", e);
+                Settings::default()
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("settings serialisation")
+    }
+
+    fn migrate(mut self) -> Settings {
+        // no migrations yet, schema 1 is the first stored one
+        self.schema_version = SETTINGS_SCHEMA_VERSION;
+        self
+    }
+}
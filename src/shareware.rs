@@ -0,0 +1,46 @@
+// The shareware data compiled into the desktop build. The browser fetches
+// the same files from public/shareware when they are needed.
+
+const SHAREWARE_FILES: [(&str, &[u8]); 9] = [
+    (
+        "AUDIOHED.WL1",
+        include_bytes!("../public/shareware/AUDIOHED.WL1"),
+    ),
+    (
+        "AUDIOT.WL1",
+        include_bytes!("../public/shareware/AUDIOT.WL1"),
+    ),
+    (
+        "CONFIG.WL1",
+        include_bytes!("../public/shareware/CONFIG.WL1"),
+    ),
+    (
+        "GAMEMAPS.WL1",
+        include_bytes!("../public/shareware/GAMEMAPS.WL1"),
+    ),
+    (
+        "MAPHEAD.WL1",
+        include_bytes!("../public/shareware/MAPHEAD.WL1"),
+    ),
+    (
+        "VGADICT.WL1",
+        include_bytes!("../public/shareware/VGADICT.WL1"),
+    ),
+    (
+        "VGAGRAPH.WL1",
+        include_bytes!("../public/shareware/VGAGRAPH.WL1"),
+    ),
+    (
+        "VGAHEAD.WL1",
+        include_bytes!("../public/shareware/VGAHEAD.WL1"),
+    ),
+    ("VSWAP.WL1", include_bytes!("../public/shareware/VSWAP.WL1")),
+];
+
+/// All shareware files, uploaded files have to be written afterwards to
+/// replace them.
+pub fn shareware_files() -> impl Iterator<Item = (String, &'static [u8])> {
+    SHAREWARE_FILES
+        .into_iter()
+        .map(|(name, data)| (name.to_string(), data))
+}
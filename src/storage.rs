@@ -0,0 +1,420 @@
+// Persistent key value storage of the player. In the browser the values
+// live in IndexedDB (one database per `db`, one object store per `store`),
+// on the desktop in files below the XDG data dir (`<db>/<store>/<key>`).
+// Keys may contain '/', on the desktop these become directories.
+
+#[cfg(not(feature = "web"))]
+pub use files::FileStorage;
+#[cfg(feature = "web")]
+pub use indexeddb::IndexedDbStorage;
+#[cfg(test)]
+pub use memory::MemoryStorage;
+
+/// The storage the player uses on this platform.
+#[cfg(feature = "web")]
+pub type PlatformStorage = IndexedDbStorage;
+#[cfg(not(feature = "web"))]
+pub type PlatformStorage = FileStorage;
+
+/// Implementations are cheap to clone, the clones share their data.
+pub trait Storage: Clone + 'static {
+    async fn put_file(&self, key: &str, data: &[u8], db: &str, store: &str) -> Result<(), String>;
+
+    async fn put_text(&self, key: &str, text: &str, db: &str, store: &str) -> Result<(), String> {
+        self.put_file(key, text.as_bytes(), db, store).await
+    }
+
+    async fn load_file(&self, key: &str, db: &str, store: &str) -> Result<Vec<u8>, String>;
+
+    async fn load_text(&self, key: &str, db: &str, store: &str) -> Result<String, String> {
+        let data = self.load_file(key, db, store).await?;
+        String::from_utf8(data).map_err(|e| e.to_string())
+    }
+
+    async fn delete_file(&self, key: &str, db: &str, store: &str) -> Result<(), String>;
+
+    async fn list_files(&self, db: &str, store: &str) -> Result<Vec<String>, String>;
+}
+
+#[cfg(feature = "web")]
+mod indexeddb {
+    use js_sys::{Array, Uint8Array};
+    use wasm_bindgen::prelude::*;
+    use wasm_bindgen_futures::JsFuture;
+
+    use super::Storage;
+
+    #[derive(Clone, Default)]
+    pub struct IndexedDbStorage;
+
+    impl Storage for IndexedDbStorage {
+        async fn put_file(
+            &self,
+            key: &str,
+            data: &[u8],
+            db: &str,
+            store: &str,
+        ) -> Result<(), String> {
+            put_value(key, &Uint8Array::from(data), db, store)
+                .await
+                .map_err(|e| format!("{:?}", e))
+        }
+
+        // texts are stored as JS strings, not as bytes
+        async fn put_text(
+            &self,
+            key: &str,
+            text: &str,
+            db: &str,
+            store: &str,
+        ) -> Result<(), String> {
+            put_value(key, &JsValue::from_str(text), db, store)
+                .await
+                .map_err(|e| format!("{:?}", e))
+        }
+
+        async fn load_file(&self, key: &str, db: &str, store: &str) -> Result<Vec<u8>, String> {
+            let value = load_value(key, db, store)
+                .await
+                .map_err(|e| format!("{:?}", e))?;
+            Ok(Uint8Array::new(&value).to_vec())
+        }
+
+        async fn load_text(&self, key: &str, db: &str, store: &str) -> Result<String, String> {
+            let value = load_value(key, db, store)
+                .await
+                .map_err(|e| format!("{:?}", e))?;
+            value
+                .as_string()
+                .ok_or_else(|| format!("{} is not a text", key))
+        }
+
+        async fn delete_file(&self, key: &str, db: &str, store: &str) -> Result<(), String> {
+            delete_value(key, db, store)
+                .await
+                .map_err(|e| format!("{:?}", e))
+        }
+
+        async fn list_files(&self, db: &str, store: &str) -> Result<Vec<String>, String> {
+            list_keys(db, store).await.map_err(|e| format!("{:?}", e))
+        }
+    }
+
+    async fn put_value(key: &str, value: &JsValue, db: &str, store: &str) -> Result<(), JsValue> {
+        let db = open_db(db, store).await?;
+        let transaction =
+            db.transaction_with_str_and_mode(store, web_sys::IdbTransactionMode::Readwrite)?;
+
+        let store = transaction.object_store(store)?;
+        idb_request_await(&store.put_with_key(value, &key.into())?)
+            .await
+            .map_err(|_| "idb store failed")?;
+        Ok(())
+    }
+
+    async fn delete_value(key: &str, db: &str, store: &str) -> Result<(), JsValue> {
+        let db = open_db(db, store).await?;
+        let transaction =
+            db.transaction_with_str_and_mode(store, web_sys::IdbTransactionMode::Readwrite)?;
+
+        let store = transaction.object_store(store)?;
+        idb_request_await(&store.delete(&key.into())?)
+            .await
+            .map_err(|_| "idb delete failed")?;
+        Ok(())
+    }
+
+    async fn list_keys(db: &str, store: &str) -> Result<Vec<String>, JsValue> {
+        let db = open_db(db, store).await?;
+        let transaction =
+            db.transaction_with_str_and_mode(store, web_sys::IdbTransactionMode::Readonly)?;
+        let store = transaction.object_store(store)?;
+
+        let keys = idb_request_await(&store.get_all_keys()?)
+            .await
+            .map_err(|_| "idb list failed")?;
+        Ok(Array::from(&keys)
+            .iter()
+            .filter_map(|key| key.as_string())
+            .collect())
+    }
+
+    async fn load_value(key: &str, db: &str, store: &str) -> Result<JsValue, JsValue> {
+        let db = open_db(db, store).await?;
+        let transaction =
+            db.transaction_with_str_and_mode(store, web_sys::IdbTransactionMode::Readwrite)?;
+        let store = transaction.object_store(store)?;
+
+        let value = idb_request_await(&store.get(&key.into())?)
+            .await
+            .map_err(|_| "idb load failed")?;
+        if value.is_undefined() {
+            Err(JsValue::NULL)
+        } else {
+            Ok(value)
+        }
+    }
+
+    async fn open_db(db: &str, store: &str) -> Result<web_sys::IdbDatabase, JsValue> {
+        let window = web_sys::window().expect("global window access");
+        let factory = window.indexed_db()?;
+        if let Some(factory) = factory {
+            let open_request = factory.open(db)?;
+
+            let db_promise = js_sys::Promise::new(&mut |resolve, reject| {
+                let onsuccess = Closure::wrap(Box::new(move |event: web_sys::Event| {
+                    let db = web_sys::IdbDatabase::from(
+                        js_sys::Reflect::get(&event, &JsValue::from_str("target"))
+                            .unwrap()
+                            .dyn_into::<web_sys::IdbOpenDbRequest>()
+                            .unwrap()
+                            .result()
+                            .unwrap(),
+                    );
+                    resolve.call1(&JsValue::NULL, &db).unwrap();
+                }) as Box<dyn FnMut(_)>);
+
+                let onerror = Closure::wrap(Box::new(move |_event: web_sys::Event| {
+                    let error = "opening IndexDB failed".into();
+                    reject.call1(&JsValue::NULL, &error).unwrap();
+                }) as Box<dyn FnMut(_)>);
+
+                let onupgradeneeded = Closure::wrap(Box::new(move |event: web_sys::Event| {
+                    let db = web_sys::IdbDatabase::from(
+                        js_sys::Reflect::get(&event, &JsValue::from_str("target"))
+                            .unwrap()
+                            .dyn_into::<web_sys::IdbOpenDbRequest>()
+                            .unwrap()
+                            .result()
+                            .unwrap(),
+                    );
+
+                    if !db.object_store_names().contains(store) {
+                        db.create_object_store(store).expect("created store");
+                    }
+                }) as Box<dyn FnMut(_)>);
+
+                open_request.set_onsuccess(Some(onsuccess.as_ref().unchecked_ref()));
+                open_request.set_onerror(Some(onerror.as_ref().unchecked_ref()));
+                open_request.set_onupgradeneeded(Some(onupgradeneeded.as_ref().unchecked_ref()));
+                onsuccess.forget();
+                onerror.forget();
+                onupgradeneeded.forget();
+            });
+
+            let db = JsFuture::from(db_promise).await?;
+            let db = web_sys::IdbDatabase::from(db);
+            Ok(db)
+        } else {
+            Err("could not access IndexDB".into())
+        }
+    }
+
+    async fn idb_request_await(request: &web_sys::IdbRequest) -> Result<JsValue, JsValue> {
+        let promise = js_sys::Promise::new(&mut |resolve, reject| {
+            let on_success = Closure::once(move |_: web_sys::Event| {
+                resolve.call0(&JsValue::NULL).unwrap();
+            });
+            let on_error = Closure::once(move |e: JsValue| {
+                reject.call1(&JsValue::NULL, &e).unwrap();
+            });
+
+            request.set_onsuccess(Some(on_success.as_ref().unchecked_ref()));
+            request.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+
+            on_success.forget();
+            on_error.forget();
+        });
+        JsFuture::from(promise).await?;
+        request.result()
+    }
+}
+
+#[cfg(not(feature = "web"))]
+mod files {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use super::Storage;
+
+    const DATA_DIR_NAME: &str = "iron-wolf-player";
+
+    #[derive(Clone)]
+    pub struct FileStorage {
+        root: PathBuf,
+    }
+
+    impl FileStorage {
+        pub fn new(root: PathBuf) -> FileStorage {
+            FileStorage { root }
+        }
+
+        /// The directory the values of the store are files in.
+        pub fn store_path(&self, db: &str, store: &str) -> PathBuf {
+            self.root.join(db).join(store)
+        }
+
+        fn value_path(&self, key: &str, db: &str, store: &str) -> Result<PathBuf, String> {
+            // keys come from file names, never let them leave the store
+            if key
+                .split('/')
+                .any(|part| part.is_empty() || part == "." || part == "..")
+            {
+                return Err(format!("invalid key: {}", key));
+            }
+            Ok(self.store_path(db, store).join(key))
+        }
+    }
+
+    /// `$XDG_DATA_HOME/iron-wolf-player`, `~/.local/share/iron-wolf-player`
+    /// if XDG_DATA_HOME is not set. Falls back to the working directory
+    /// without a HOME.
+    impl Default for FileStorage {
+        fn default() -> FileStorage {
+            let base = match std::env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+                Some(dir) => PathBuf::from(dir),
+                None => match std::env::var_os("HOME") {
+                    Some(home) => PathBuf::from(home).join(".local").join("share"),
+                    None => PathBuf::new(),
+                },
+            };
+            FileStorage::new(base.join(DATA_DIR_NAME))
+        }
+    }
+
+    impl Storage for FileStorage {
+        async fn put_file(
+            &self,
+            key: &str,
+            data: &[u8],
+            db: &str,
+            store: &str,
+        ) -> Result<(), String> {
+            let path = self.value_path(key, db, store)?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            fs::write(&path, data).map_err(|e| format!("{}: {}", path.display(), e))
+        }
+
+        async fn load_file(&self, key: &str, db: &str, store: &str) -> Result<Vec<u8>, String> {
+            let path = self.value_path(key, db, store)?;
+            fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))
+        }
+
+        async fn delete_file(&self, key: &str, db: &str, store: &str) -> Result<(), String> {
+            let path = self.value_path(key, db, store)?;
+            fs::remove_file(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            // drop the directory of an installation with its last file
+            if let Some(parent) = path.parent() {
+                let _ = fs::remove_dir(parent);
+            }
+            Ok(())
+        }
+
+        async fn list_files(&self, db: &str, store: &str) -> Result<Vec<String>, String> {
+            let root = self.root.join(db).join(store);
+            let mut keys = Vec::new();
+            if root.is_dir() {
+                collect_keys(&root, "", &mut keys).map_err(|e| e.to_string())?;
+            }
+            Ok(keys)
+        }
+    }
+
+    fn collect_keys(dir: &Path, prefix: &str, keys: &mut Vec<String>) -> std::io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let key = format!("{}{}", prefix, name);
+            if entry.file_type()?.is_dir() {
+                collect_keys(&entry.path(), &format!("{}/", key), keys)?;
+            } else {
+                keys.push(key);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod memory {
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+    use std::rc::Rc;
+
+    use super::Storage;
+
+    // (db, store, key) -> data
+    type Files = BTreeMap<(String, String, String), Vec<u8>>;
+
+    /// Keeps everything in memory, nothing survives a restart. Meant for
+    /// exercising the upload and persistence logic without a browser.
+    #[derive(Clone, Default)]
+    pub struct MemoryStorage {
+        files: Rc<RefCell<Files>>,
+    }
+
+    // synchronous access for the tests, nothing here ever has to wait
+    impl MemoryStorage {
+        /// Stores each key with its own name as the data.
+        pub fn put_keys(&self, db: &str, store: &str, keys: &[&str]) {
+            for key in keys {
+                self.put(key, key.as_bytes(), db, store);
+            }
+        }
+
+        pub fn put(&self, key: &str, data: &[u8], db: &str, store: &str) {
+            self.files.borrow_mut().insert(
+                (db.to_string(), store.to_string(), key.to_string()),
+                data.to_vec(),
+            );
+        }
+
+        pub fn get(&self, key: &str, db: &str, store: &str) -> Option<Vec<u8>> {
+            self.files
+                .borrow()
+                .get(&(db.to_string(), store.to_string(), key.to_string()))
+                .cloned()
+        }
+
+        pub fn keys(&self, db: &str, store: &str) -> Vec<String> {
+            self.files
+                .borrow()
+                .keys()
+                .filter(|(d, s, _)| d == db && s == store)
+                .map(|(_, _, key)| key.clone())
+                .collect()
+        }
+    }
+
+    impl Storage for MemoryStorage {
+        async fn put_file(
+            &self,
+            key: &str,
+            data: &[u8],
+            db: &str,
+            store: &str,
+        ) -> Result<(), String> {
+            self.put(key, data, db, store);
+            Ok(())
+        }
+
+        async fn load_file(&self, key: &str, db: &str, store: &str) -> Result<Vec<u8>, String> {
+            self.get(key, db, store)
+                .ok_or_else(|| format!("{} not found", key))
+        }
+
+        async fn delete_file(&self, key: &str, db: &str, store: &str) -> Result<(), String> {
+            self.files
+                .borrow_mut()
+                .remove(&(db.to_string(), store.to_string(), key.to_string()))
+                .map(|_| ())
+                .ok_or_else(|| format!("{} not found", key))
+        }
+
+        async fn list_files(&self, db: &str, store: &str) -> Result<Vec<String>, String> {
+            Ok(self.keys(db, store))
+        }
+    }
+}
@@ -0,0 +1,21 @@
+// Running the player's futures. In the browser they run on the JS event
+// loop, on the desktop they are run to completion right away: they only
+// wait for storage and file dialogs there, which block anyway.
+
+use std::future::Future;
+
+use poll_promise::Promise;
+
+pub fn spawn_local(future: impl Future<Output = ()> + 'static) {
+    #[cfg(feature = "web")]
+    wasm_bindgen_futures::spawn_local(future);
+    #[cfg(not(feature = "web"))]
+    pollster::block_on(future);
+}
+
+pub fn spawn_promise<T: Send + 'static>(future: impl Future<Output = T> + 'static) -> Promise<T> {
+    #[cfg(feature = "web")]
+    return Promise::spawn_local(future);
+    #[cfg(not(feature = "web"))]
+    return Promise::from_ready(pollster::block_on(future));
+}
@@ -0,0 +1,294 @@
+// On-screen controls for touch devices: a virtual stick on one side and
+// buttons on the other. Every finger is tracked on its own (egui touch
+// events), so moving, firing and strafing work at the same time. Like the
+// gamepad, the controls only produce the keys the engine understands.
+
+#[cfg(feature = "web")]
+use egui::{Color32, Pos2, Rect, Stroke, TouchId, TouchPhase, Vec2, pos2};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "web")]
+use web_sys::window;
+
+#[cfg(feature = "web")]
+use crate::key_bindings::key_changes;
+
+// stick deflection (relative to its radius) a direction needs to count
+#[cfg(feature = "web")]
+const STICK_THRESHOLD: f32 = 0.3;
+#[cfg(feature = "web")]
+const MARGIN: f32 = 20.0;
+#[cfg(feature = "web")]
+const STROKE_WIDTH: f32 = 2.0;
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TouchMode {
+    // shown on devices with a touch screen
+    Auto,
+    Always,
+    Never,
+}
+
+#[cfg(feature = "web")]
+impl TouchMode {
+    pub const ALL: [TouchMode; 3] = [TouchMode::Auto, TouchMode::Always, TouchMode::Never];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            TouchMode::Auto => "On touch screens",
+            TouchMode::Always => "Always",
+            TouchMode::Never => "Never",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TouchSettings {
+    pub mode: TouchMode,
+    pub opacity: f32,
+    // radius of the buttons, the stick is twice as large
+    pub size: f32,
+    // stick on the right, buttons on the left
+    pub left_handed: bool,
+}
+
+impl Default for TouchSettings {
+    fn default() -> TouchSettings {
+        TouchSettings {
+            mode: TouchMode::Auto,
+            opacity: 0.5,
+            size: 32.0,
+            left_handed: false,
+        }
+    }
+}
+
+#[cfg(feature = "web")]
+impl TouchSettings {
+    pub fn is_active(&self) -> bool {
+        match self.mode {
+            TouchMode::Auto => has_touch_screen(),
+            TouchMode::Always => true,
+            TouchMode::Never => false,
+        }
+    }
+}
+
+#[cfg(feature = "web")]
+struct TouchButton {
+    center: Pos2,
+    radius: f32,
+    key: &'static str,
+    label: &'static str,
+}
+
+#[cfg(feature = "web")]
+struct Layout {
+    stick_center: Pos2,
+    stick_radius: f32,
+    buttons: Vec<TouchButton>,
+}
+
+#[cfg(feature = "web")]
+impl Layout {
+    /// The controls in the corners of the game area.
+    fn new(area: Rect, settings: &TouchSettings) -> Layout {
+        let size = settings.size;
+        // positions as if right-handed, mirrored for left-handed players
+        let place = |x_from_right: f32, y: f32| {
+            let x = if settings.left_handed {
+                area.left() + x_from_right
+            } else {
+                area.right() - x_from_right
+            };
+            pos2(x, y)
+        };
+        let stick_radius = 2.0 * size;
+        let stick_x = MARGIN + stick_radius;
+        let stick_center = if settings.left_handed {
+            pos2(
+                area.right() - stick_x,
+                area.bottom() - MARGIN - stick_radius,
+            )
+        } else {
+            pos2(area.left() + stick_x, area.bottom() - MARGIN - stick_radius)
+        };
+
+        let bottom = area.bottom() - MARGIN - size;
+        let step = 2.0 * size + MARGIN / 2.0;
+        let mut buttons = vec![
+            TouchButton {
+                center: place(MARGIN + size, bottom),
+                radius: size,
+                key: "Control",
+                label: "Fire",
+            },
+            TouchButton {
+                center: place(MARGIN + size + step, bottom),
+                radius: size,
+                key: " ",
+                label: "Use",
+            },
+            TouchButton {
+                center: place(MARGIN + size, bottom - step),
+                radius: size,
+                key: "Alt",
+                label: "Strafe",
+            },
+            TouchButton {
+                center: place(MARGIN + size, area.top() + MARGIN + size),
+                radius: size,
+                key: "Escape",
+                label: "Menu",
+            },
+        ];
+        // the weapons in a row of smaller buttons left of the menu button
+        for (ix, key) in ["1", "2", "3", "4"].into_iter().enumerate() {
+            let x = MARGIN + size + step + ix as f32 * (size + MARGIN / 2.0);
+            buttons.push(TouchButton {
+                center: place(x, area.top() + MARGIN + size),
+                radius: size / 2.0,
+                key,
+                label: key,
+            });
+        }
+
+        Layout {
+            stick_center,
+            stick_radius,
+            buttons,
+        }
+    }
+
+    fn keys_at(&self, pos: Pos2) -> Vec<&'static str> {
+        let offset = (pos - self.stick_center) / self.stick_radius;
+        // a bit of slack so the finger may leave the stick while moving
+        if offset.length() <= 1.5 {
+            let mut keys = Vec::new();
+            if offset.y < -STICK_THRESHOLD {
+                keys.push("ArrowUp");
+            } else if offset.y > STICK_THRESHOLD {
+                keys.push("ArrowDown");
+            }
+            if offset.x < -STICK_THRESHOLD {
+                keys.push("ArrowLeft");
+            } else if offset.x > STICK_THRESHOLD {
+                keys.push("ArrowRight");
+            }
+            return keys;
+        }
+
+        self.buttons
+            .iter()
+            .filter(|button| button.center.distance(pos) <= button.radius)
+            .map(|button| button.key)
+            .collect()
+    }
+}
+
+#[cfg(feature = "web")]
+#[derive(Default)]
+pub struct TouchControls {
+    // active fingers and where they are
+    touches: Vec<(TouchId, Pos2)>,
+    held: Vec<String>,
+}
+
+#[cfg(feature = "web")]
+impl TouchControls {
+    /// Tracks the fingers of this frame's touch events. Returns the keys
+    /// that went down (true) or up (false).
+    pub fn update(
+        &mut self,
+        events: &[egui::Event],
+        area: Rect,
+        settings: &TouchSettings,
+    ) -> Vec<(String, bool)> {
+        for event in events {
+            if let egui::Event::Touch { id, phase, pos, .. } = event {
+                self.touches.retain(|(touch, _)| touch != id);
+                if matches!(phase, TouchPhase::Start | TouchPhase::Move) {
+                    self.touches.push((*id, *pos));
+                }
+            }
+        }
+
+        let layout = Layout::new(area, settings);
+        let mut held: Vec<String> = Vec::new();
+        for (_, pos) in &self.touches {
+            for key in layout.keys_at(*pos) {
+                if !held.iter().any(|k| k == key) {
+                    held.push(key.to_string());
+                }
+            }
+        }
+
+        let changes = key_changes(&self.held, &held);
+        self.held = held;
+        changes
+    }
+
+    /// Releases all keys, e.g. when the controls get hidden.
+    pub fn release(&mut self) -> Vec<(String, bool)> {
+        self.touches.clear();
+        let changes = key_changes(&self.held, &[]);
+        self.held.clear();
+        changes
+    }
+
+    pub fn paint(
+        &self,
+        painter: &egui::Painter,
+        area: Rect,
+        settings: &TouchSettings,
+        colour: Color32,
+    ) {
+        let layout = Layout::new(area, settings);
+        let colour = colour.linear_multiply(settings.opacity);
+        let is_held = |key: &str| self.held.iter().any(|k| k == key);
+
+        painter.circle_stroke(
+            layout.stick_center,
+            layout.stick_radius,
+            Stroke::new(STROKE_WIDTH, colour),
+        );
+        // the knob follows the finger on the stick
+        let knob = self
+            .touches
+            .iter()
+            .map(|(_, pos)| *pos - layout.stick_center)
+            .find(|offset| offset.length() <= 1.5 * layout.stick_radius)
+            .map_or(Vec2::ZERO, |offset| {
+                offset.normalized() * offset.length().min(layout.stick_radius)
+            });
+        painter.circle_filled(
+            layout.stick_center + knob,
+            layout.stick_radius / 2.0,
+            colour,
+        );
+
+        for button in &layout.buttons {
+            if is_held(button.key) {
+                painter.circle_filled(button.center, button.radius, colour);
+            } else {
+                painter.circle_stroke(
+                    button.center,
+                    button.radius,
+                    Stroke::new(STROKE_WIDTH, colour),
+                );
+            }
+            painter.text(
+                button.center,
+                egui::Align2::CENTER_CENTER,
+                button.label,
+                egui::FontId::proportional(button.radius * 0.6),
+                colour,
+            );
+        }
+    }
+}
+
+#[cfg(feature = "web")]
+fn has_touch_screen() -> bool {
+    window().is_some_and(|w| w.navigator().max_touch_points() > 0)
+}
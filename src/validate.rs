@@ -0,0 +1,319 @@
+// Structural checks of the Wolfenstein 3-D data files. They only look at
+// the headers and how the files reference each other, enough to catch
+// truncated files or files that belong to another game before the
+// engine trips over them. Where the engine has numbers for the game
+// (version.rs) the chunk counts have to match them, that catches files
+// renamed to the extension of another game.
+
+use crate::version::DataLayout;
+
+const RLEW_TAG: u16 = 0xABCD;
+const MAPHEAD_NUM_MAPS: usize = 100;
+const GAMEMAPS_SIGNATURE: &[u8] = b"TED5v1.0";
+// 3 plane starts (i32), 3 plane lengths (u16), width, height (u16), name (16 chars)
+const MAP_HEADER_SIZE: usize = 38;
+const VGADICT_SIZE: usize = 1024; // 256 huffman nodes with two u16 each
+const VGAHEAD_SPARSE: u32 = 0x00FF_FFFF;
+// the digitized sounds in AUDIOT are empty or just this marker
+const AUDIOT_ID_MARKER: &[u8] = b"!ID!";
+
+pub fn validate_maphead(maphead: &[u8], gamemaps: Option<&[u8]>) -> Result<(), String> {
+    if maphead.len() < 2 + MAPHEAD_NUM_MAPS * 4 {
+        return Err(format!("too short ({} bytes)", maphead.len()));
+    }
+    let tag = u16_at(maphead, 0);
+    if tag != RLEW_TAG {
+        return Err(format!(
+            "wrong magic 0x{:04X}, expected 0x{:04X}",
+            tag, RLEW_TAG
+        ));
+    }
+
+    if let Some(gamemaps) = gamemaps {
+        for map in 0..MAPHEAD_NUM_MAPS {
+            let offset = i32_at(maphead, 2 + map * 4);
+            if offset <= 0 {
+                continue; // map not present
+            }
+            if offset as usize + MAP_HEADER_SIZE > gamemaps.len() {
+                return Err(format!(
+                    "map {} starts at {}, GAMEMAPS has only {} bytes",
+                    map,
+                    offset,
+                    gamemaps.len()
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn validate_gamemaps(gamemaps: &[u8]) -> Result<(), String> {
+    if !gamemaps.starts_with(GAMEMAPS_SIGNATURE) {
+        return Err("missing TED5v1.0 signature".to_string());
+    }
+    Ok(())
+}
+
+pub fn validate_vswap(vswap: &[u8], layout: Option<&DataLayout>) -> Result<(), String> {
+    if vswap.len() < 6 {
+        return Err(format!("too short ({} bytes)", vswap.len()));
+    }
+    let chunks = u16_at(vswap, 0) as usize;
+    let sprite_start = u16_at(vswap, 2) as usize;
+    let sound_start = u16_at(vswap, 4) as usize;
+    if chunks == 0 || sprite_start > sound_start || sound_start > chunks {
+        return Err(format!(
+            "inconsistent chunk counts (chunks {}, sprites at {}, sounds at {})",
+            chunks, sprite_start, sound_start
+        ));
+    }
+    if let Some(layout) = layout {
+        if let Some(expected) = layout.sprite_start
+            && sprite_start != expected
+        {
+            return Err(format!(
+                "sprites start at chunk {}, the game has them at {}",
+                sprite_start, expected
+            ));
+        }
+        if let Some(expected) = layout.num_sprites
+            && sound_start - sprite_start != expected
+        {
+            return Err(format!(
+                "has {} sprites, the game has {}",
+                sound_start - sprite_start,
+                expected
+            ));
+        }
+    }
+
+    let lengths_start = 6 + chunks * 4;
+    let header_end = lengths_start + chunks * 2;
+    if vswap.len() < header_end {
+        return Err(format!(
+            "header of {} chunks does not fit into {} bytes",
+            chunks,
+            vswap.len()
+        ));
+    }
+    for chunk in 0..chunks {
+        let offset = u32_at(vswap, 6 + chunk * 4) as usize;
+        if offset == 0 {
+            continue; // sparse chunk
+        }
+        let length = u16_at(vswap, lengths_start + chunk * 2) as usize;
+        let end = offset.checked_add(length);
+        if end.is_none_or(|end| end > vswap.len()) {
+            return Err(format!(
+                "chunk {} ({} bytes at {}) does not fit into {} bytes",
+                chunk,
+                length,
+                offset,
+                vswap.len()
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn validate_vgadict(vgadict: &[u8]) -> Result<(), String> {
+    if vgadict.len() != VGADICT_SIZE {
+        return Err(format!(
+            "has {} bytes, expected {}",
+            vgadict.len(),
+            VGADICT_SIZE
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_vgahead(
+    vgahead: &[u8],
+    vgagraph: Option<&[u8]>,
+    layout: Option<&DataLayout>,
+) -> Result<(), String> {
+    if vgahead.is_empty() || !vgahead.len().is_multiple_of(3) {
+        return Err(format!(
+            "size {} is not a multiple of 3 bytes",
+            vgahead.len()
+        ));
+    }
+    // the last entry is the end of the last chunk
+    let chunks = vgahead.len() / 3 - 1;
+    if let Some(layout) = layout {
+        let needed = (layout.start_pics + layout.num_pics).max(layout.start_end_text + 1);
+        if chunks < needed {
+            return Err(format!(
+                "has {} chunks, the game needs at least {}",
+                chunks, needed
+            ));
+        }
+    }
+
+    if let Some(vgagraph) = vgagraph {
+        for (chunk, entry) in vgahead.chunks_exact(3).enumerate() {
+            let offset = u32::from_le_bytes([entry[0], entry[1], entry[2], 0]);
+            if offset == VGAHEAD_SPARSE {
+                continue;
+            }
+            if offset as usize > vgagraph.len() {
+                return Err(format!(
+                    "chunk {} starts at {}, VGAGRAPH has only {} bytes",
+                    chunk,
+                    offset,
+                    vgagraph.len()
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn validate_audiohed(
+    audiohed: &[u8],
+    audiot: Option<&[u8]>,
+    layout: Option<&DataLayout>,
+) -> Result<(), String> {
+    if audiohed.len() < 8 || !audiohed.len().is_multiple_of(4) {
+        return Err(format!(
+            "size {} is not a multiple of 4 bytes",
+            audiohed.len()
+        ));
+    }
+
+    let mut last = 0;
+    for (chunk, entry) in audiohed.chunks_exact(4).enumerate() {
+        let offset = u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]);
+        if offset < last {
+            return Err(format!("offset of chunk {} is not ascending", chunk));
+        }
+        last = offset;
+    }
+
+    if let Some(audiot) = audiot
+        && last as usize > audiot.len()
+    {
+        return Err(format!(
+            "chunks end at {}, AUDIOT has only {} bytes",
+            last,
+            audiot.len()
+        ));
+    }
+
+    // the music follows the digitized sounds
+    if let (Some(audiot), Some(layout)) = (audiot, layout) {
+        let offset = |ix: usize| {
+            let entry = audiohed.get(ix * 4..ix * 4 + 4)?;
+            Some(u32_at(entry, 0) as usize)
+        };
+        let chunk = |ix: usize| audiot.get(offset(ix)?..offset(ix + 1)?);
+        let music = layout.start_music;
+        let digitized_end = music
+            .checked_sub(1)
+            .and_then(chunk)
+            .is_some_and(|data| data.is_empty() || data == AUDIOT_ID_MARKER);
+        let first_track = chunk(music).is_some_and(|data| !data.is_empty());
+        if !digitized_end || !first_track {
+            return Err(format!(
+                "the music does not start at chunk {} like in the game",
+                music
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn u16_at(data: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([data[pos], data[pos + 1]])
+}
+
+fn u32_at(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+}
+
+fn i32_at(data: &[u8], pos: usize) -> i32 {
+    i32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::GameVersion;
+
+    const AUDIOHED: &[u8] = include_bytes!("../public/shareware/AUDIOHED.WL1");
+    const AUDIOT: &[u8] = include_bytes!("../public/shareware/AUDIOT.WL1");
+    const GAMEMAPS: &[u8] = include_bytes!("../public/shareware/GAMEMAPS.WL1");
+    const MAPHEAD: &[u8] = include_bytes!("../public/shareware/MAPHEAD.WL1");
+    const VGADICT: &[u8] = include_bytes!("../public/shareware/VGADICT.WL1");
+    const VGAGRAPH: &[u8] = include_bytes!("../public/shareware/VGAGRAPH.WL1");
+    const VGAHEAD: &[u8] = include_bytes!("../public/shareware/VGAHEAD.WL1");
+    const VSWAP: &[u8] = include_bytes!("../public/shareware/VSWAP.WL1");
+
+    fn set_u16(data: &mut [u8], pos: usize, value: u16) {
+        data[pos..pos + 2].copy_from_slice(&value.to_le_bytes());
+    }
+
+    #[test]
+    fn accepts_the_shareware() {
+        let layout = GameVersion::WL1.layout();
+        let layout = layout.as_ref();
+        assert_eq!(validate_audiohed(AUDIOHED, Some(AUDIOT), layout), Ok(()));
+        assert_eq!(validate_gamemaps(GAMEMAPS), Ok(()));
+        assert_eq!(validate_maphead(MAPHEAD, Some(GAMEMAPS)), Ok(()));
+        assert_eq!(validate_vgadict(VGADICT), Ok(()));
+        assert_eq!(validate_vgahead(VGAHEAD, Some(VGAGRAPH), layout), Ok(()));
+        assert_eq!(validate_vswap(VSWAP, layout), Ok(()));
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let layout = GameVersion::WL1.layout();
+        let layout = layout.as_ref();
+        assert!(validate_vswap(&VSWAP[..VSWAP.len() / 2], layout).is_err());
+        assert!(validate_vswap(&VSWAP[..1000], layout).is_err());
+        assert!(validate_vswap(&VSWAP[..4], layout).is_err());
+        assert!(validate_audiohed(AUDIOHED, Some(&AUDIOT[..1000]), layout).is_err());
+        assert!(validate_maphead(MAPHEAD, Some(&GAMEMAPS[..100])).is_err());
+        assert!(validate_vgahead(VGAHEAD, Some(&VGAGRAPH[..1000]), layout).is_err());
+        assert!(validate_vgahead(&VGAHEAD[..VGAHEAD.len() - 1], None, layout).is_err());
+    }
+
+    #[test]
+    fn rejects_corrupted_headers() {
+        let mut maphead = MAPHEAD.to_vec();
+        maphead[0] ^= 0xFF;
+        assert!(validate_maphead(&maphead, None).is_err());
+
+        let mut audiohed = AUDIOHED.to_vec();
+        audiohed[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(validate_audiohed(&audiohed, None, None).is_err());
+
+        // sprites after the sounds
+        let mut vswap = VSWAP.to_vec();
+        set_u16(&mut vswap, 2, 600);
+        assert!(validate_vswap(&vswap, None).is_err());
+    }
+
+    #[test]
+    fn rejects_chunk_offsets_past_the_file() {
+        let mut vswap = VSWAP.to_vec();
+        vswap[6..10].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(validate_vswap(&vswap, None).is_err());
+    }
+
+    #[test]
+    fn rejects_files_of_another_game() {
+        // more walls than Wolfenstein 3-D, as in Spear of Destiny
+        let mut vswap = VSWAP.to_vec();
+        set_u16(&mut vswap, 2, 134);
+        let wl6 = GameVersion::WL6.layout();
+        assert!(validate_vswap(&vswap, wl6.as_ref()).is_err());
+        let sod = GameVersion::SOD.layout();
+        assert_eq!(validate_vswap(&vswap, sod.as_ref()), Ok(()));
+
+        assert!(validate_audiohed(AUDIOHED, Some(AUDIOT), sod.as_ref()).is_err());
+        // the shareware has fewer graphics than Spear of Destiny
+        assert!(validate_vgahead(VGAHEAD, Some(VGAGRAPH), sod.as_ref()).is_err());
+    }
+}
@@ -0,0 +1,153 @@
+#[cfg(any(feature = "web", feature = "desktop"))]
+use iw::assets::WolfVariant;
+
+/// Where the parts of the data files of a game start, the numbers the
+/// engine's WolfVariant has for the game.
+pub struct DataLayout {
+    // VGAHEAD chunks
+    pub start_pics: usize,
+    pub num_pics: usize,
+    pub start_end_text: usize,
+    // AUDIOT chunk of the first music track
+    pub start_music: usize,
+    // VSWAP chunks, only known for Wolfenstein 3-D
+    pub sprite_start: Option<usize>,
+    pub num_sprites: Option<usize>,
+}
+
+// the wall and door pages of Wolfenstein 3-D (as in the shareware VSWAP)
+// and the size of the engine's sprite table
+const WOLF_SPRITE_START: usize = 106;
+const WOLF_NUM_SPRITES: usize = 436;
+
+/// The games (and their versions) the player can run. They are told
+/// apart by the extension of the data files. The declaration order
+/// is the order of preference if files of several games are found.
+// the variants are named like the extensions
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum GameVersion {
+    WL1,
+    WL3,
+    WL6,
+    SDM,
+    SOD,
+    SD2,
+    SD3,
+}
+
+impl GameVersion {
+    /// All versions, most preferred first.
+    pub const ALL: [GameVersion; 7] = [
+        GameVersion::SD3,
+        GameVersion::SD2,
+        GameVersion::SOD,
+        GameVersion::SDM,
+        GameVersion::WL6,
+        GameVersion::WL3,
+        GameVersion::WL1,
+    ];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            GameVersion::WL1 => "WL1",
+            GameVersion::WL3 => "WL3",
+            GameVersion::WL6 => "WL6",
+            GameVersion::SDM => "SDM",
+            GameVersion::SOD => "SOD",
+            GameVersion::SD2 => "SD2",
+            GameVersion::SD3 => "SD3",
+        }
+    }
+
+    pub fn from_extension(extension: &str) -> Option<GameVersion> {
+        GameVersion::ALL
+            .into_iter()
+            .find(|v| v.extension().eq_ignore_ascii_case(extension))
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            GameVersion::WL1 => "Wolfenstein 3D (Shareware)",
+            GameVersion::WL3 => "Wolfenstein 3D (3 Episodes)",
+            GameVersion::WL6 => "Wolfenstein 3D",
+            GameVersion::SDM => "Spear of Destiny (Demo)",
+            GameVersion::SOD => "Spear of Destiny",
+            GameVersion::SD2 => "Spear of Destiny: Return to Danger",
+            GameVersion::SD3 => "Spear of Destiny: Ultimate Challenge",
+        }
+    }
+
+    /// The games the engine can run.
+    pub fn is_supported(&self) -> bool {
+        !matches!(self, GameVersion::SDM | GameVersion::SD2 | GameVersion::SD3)
+    }
+
+    /// The layout the data files of the game are checked against, None
+    /// if the engine has no numbers for the game.
+    pub fn layout(&self) -> Option<DataLayout> {
+        match self {
+            GameVersion::WL1 => Some(DataLayout {
+                start_pics: 3,
+                num_pics: 144,
+                start_end_text: 155,
+                start_music: 261,
+                sprite_start: Some(WOLF_SPRITE_START),
+                num_sprites: Some(WOLF_NUM_SPRITES),
+            }),
+            GameVersion::WL6 => Some(DataLayout {
+                start_pics: 3,
+                num_pics: 132,
+                start_end_text: 143,
+                start_music: 261,
+                sprite_start: Some(WOLF_SPRITE_START),
+                num_sprites: Some(WOLF_NUM_SPRITES),
+            }),
+            GameVersion::SOD => Some(DataLayout {
+                start_pics: 3,
+                num_pics: 147,
+                start_end_text: 168,
+                start_music: 243,
+                sprite_start: None,
+                num_sprites: None,
+            }),
+            // the engine's WL3 variant is a placeholder
+            GameVersion::WL3 | GameVersion::SDM | GameVersion::SD2 | GameVersion::SD3 => None,
+        }
+    }
+
+    /// The engine's description of the game, None for the games the
+    /// engine does not support yet.
+    #[cfg(any(feature = "web", feature = "desktop"))]
+    pub fn variant(&self) -> Option<&'static WolfVariant> {
+        match self {
+            GameVersion::WL1 => Some(&iw::assets::W3D1),
+            GameVersion::WL3 => Some(&iw::assets::W3D3),
+            GameVersion::WL6 => Some(&iw::assets::W3D6),
+            GameVersion::SOD => Some(&iw::assets::SOD),
+            GameVersion::SDM | GameVersion::SD2 | GameVersion::SD3 => None,
+        }
+    }
+}
+
+#[cfg(all(test, any(feature = "web", feature = "desktop")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layouts_match_the_engine() {
+        for version in GameVersion::ALL {
+            let (Some(layout), Some(variant)) = (version.layout(), version.variant()) else {
+                continue;
+            };
+            assert_eq!(layout.start_pics, variant.start_pics, "{:?}", version);
+            assert_eq!(layout.num_pics, variant.num_pics, "{:?}", version);
+            assert_eq!(
+                layout.start_end_text, variant.start_end_text,
+                "{:?}",
+                version
+            );
+            assert_eq!(layout.start_music, variant.start_music, "{:?}", version);
+        }
+    }
+}
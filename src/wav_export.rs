@@ -0,0 +1,193 @@
+// Offline rendering of the music and sound effects into WAV files, with
+// the OPL generator the worklet (public/oplProcessor.js) runs. Its wasm
+// module comes from the engine, index.html keeps the processor options
+// the engine created the worklet with in `window.iw_opl_options`.
+
+use js_sys::{Array, Float32Array, Function, Object, Reflect, Uint8Array, WebAssembly};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::window;
+
+use crate::jukebox::IMF_CLOCK_RATE;
+use crate::savegame::download_file;
+
+const OPL_OPTIONS_KEY: &str = "iw_opl_options";
+const EXPORT_SAMPLE_RATE: u32 = 44100;
+// stereo frames per generate_block call
+const BLOCK_FRAMES: usize = 128;
+// an AdLib sound that never ends is cut off after this many seconds
+const MAX_SOUND_SECONDS: f64 = 30.0;
+
+pub enum ExportAudio {
+    // IMF commands and how many seconds of them
+    Music(Vec<u8>, f64),
+    // a whole AdLib sound chunk
+    Sound(Vec<u8>),
+}
+
+/// Renders the audio and offers it for download as `file_name`.
+/// Returns a status message.
+pub async fn export_wav(file_name: String, audio: ExportAudio) -> Result<String, String> {
+    let opl = OplGenerator::new().await?;
+    let samples = match audio {
+        ExportAudio::Music(imf, seconds) => opl.render_imf(&imf, seconds)?,
+        ExportAudio::Sound(adl) => opl.render_adl(&adl)?,
+    };
+    download_file(&file_name, &wav_file(&samples, EXPORT_SAMPLE_RATE))?;
+    Ok(format!("{} exported", file_name))
+}
+
+struct OplGenerator {
+    exports: Object,
+    generator: JsValue,
+    adl_clock_rate: f64,
+}
+
+impl OplGenerator {
+    /// A new generator, once the engine has created its worklet.
+    async fn new() -> Result<OplGenerator, String> {
+        let options = window()
+            .and_then(|w| Reflect::get(&w, &JsValue::from_str(OPL_OPTIONS_KEY)).ok())
+            .filter(|options| options.is_object())
+            .ok_or("the game has not set up its sound yet")?;
+        let option = |name: &str| Reflect::get(&options, &name.into()).map_err(js_error);
+
+        let wasm_bytes = Uint8Array::new(&option("wasmBytes")?).to_vec();
+        // compiling synchronously is not allowed on the main thread
+        let result = JsFuture::from(WebAssembly::instantiate_buffer(&wasm_bytes, &Object::new()))
+            .await
+            .map_err(js_error)?;
+        let instance: WebAssembly::Instance = Reflect::get(&result, &"instance".into())
+            .map_err(js_error)?
+            .dyn_into()
+            .map_err(js_error)?;
+        let exports = instance.exports();
+
+        let imf_clock_rate = option("imfClockRate")?.as_f64().unwrap_or(IMF_CLOCK_RATE);
+        let adl_clock_rate = option("adlClockRate")?.as_f64().ok_or("no adlClockRate")?;
+        let mut opl = OplGenerator {
+            exports,
+            generator: JsValue::UNDEFINED,
+            adl_clock_rate,
+        };
+        opl.generator = opl.call(
+            "new_generator",
+            &[
+                EXPORT_SAMPLE_RATE.into(),
+                imf_clock_rate.into(),
+                adl_clock_rate.into(),
+            ],
+        )?;
+        Ok(opl)
+    }
+
+    /// Interleaved stereo samples of `seconds` of the IMF music.
+    fn render_imf(&self, imf: &[u8], seconds: f64) -> Result<Vec<f32>, String> {
+        let ptr = self.copy_in(imf)?;
+        self.call(
+            "play_imf",
+            &[self.generator.clone(), ptr.clone(), imf.len().into()],
+        )?;
+        let frames = (seconds * EXPORT_SAMPLE_RATE as f64) as usize;
+        let mut samples = Vec::with_capacity(frames * 2);
+        while samples.len() < frames * 2 {
+            samples.extend(self.generate_block()?);
+        }
+        samples.truncate(frames * 2);
+        self.call("stop_imf", std::slice::from_ref(&self.generator))?;
+        self.call("dealloc", &[ptr, imf.len().into()])?;
+        Ok(samples)
+    }
+
+    /// Interleaved stereo samples of the AdLib sound chunk, until it ends.
+    fn render_adl(&self, adl: &[u8]) -> Result<Vec<f32>, String> {
+        let ptr = self.copy_in(adl)?;
+        self.call(
+            "play_adl",
+            &[self.generator.clone(), ptr.clone(), adl.len().into()],
+        )?;
+        let max_frames = (MAX_SOUND_SECONDS * EXPORT_SAMPLE_RATE as f64) as usize;
+        // one more block, for the release of the last note
+        let min_frames = (EXPORT_SAMPLE_RATE as f64 / self.adl_clock_rate) as usize + BLOCK_FRAMES;
+        let mut samples = Vec::new();
+        while samples.len() < max_frames * 2 {
+            samples.extend(self.generate_block()?);
+            let playing = self.call("is_adl_playing", std::slice::from_ref(&self.generator))?;
+            if !playing.is_truthy() && samples.len() >= min_frames * 2 {
+                break;
+            }
+        }
+        self.call("dealloc", &[ptr, adl.len().into()])?;
+        Ok(samples)
+    }
+
+    fn generate_block(&self) -> Result<Vec<f32>, String> {
+        let ptr = self.call("generate_block", std::slice::from_ref(&self.generator))?;
+        let ptr = ptr.as_f64().ok_or("generate_block returned no pointer")? as u32;
+        let block = Float32Array::new_with_byte_offset_and_length(
+            &self.memory()?,
+            ptr,
+            (BLOCK_FRAMES * 2) as u32,
+        );
+        Ok(block.to_vec())
+    }
+
+    /// Copies the data into the wasm memory, returns the pointer to it.
+    fn copy_in(&self, data: &[u8]) -> Result<JsValue, String> {
+        let ptr = self.call("alloc", &[data.len().into()])?;
+        let offset = ptr.as_f64().ok_or("alloc returned no pointer")? as u32;
+        Uint8Array::new_with_byte_offset_and_length(&self.memory()?, offset, data.len() as u32)
+            .copy_from(data);
+        Ok(ptr)
+    }
+
+    // looked up for every access, the memory buffer changes when it grows
+    fn memory(&self) -> Result<JsValue, String> {
+        let memory: WebAssembly::Memory = Reflect::get(&self.exports, &"memory".into())
+            .map_err(js_error)?
+            .dyn_into()
+            .map_err(js_error)?;
+        Ok(memory.buffer())
+    }
+
+    fn call(&self, name: &str, args: &[JsValue]) -> Result<JsValue, String> {
+        let function: Function = Reflect::get(&self.exports, &name.into())
+            .map_err(js_error)?
+            .dyn_into()
+            .map_err(|_| format!("the OPL module has no {}", name))?;
+        let args: Array = args.iter().collect();
+        function.apply(&JsValue::NULL, &args).map_err(js_error)
+    }
+}
+
+/// A 16 bit stereo PCM WAV file of the interleaved samples.
+fn wav_file(samples: &[f32], sample_rate: u32) -> Vec<u8> {
+    let channels: u16 = 2;
+    let bits: u16 = 16;
+    let block_align = channels * bits / 8;
+    let data_size = (samples.len() * 2) as u32;
+
+    let mut wav = Vec::with_capacity(44 + data_size as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_size).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+    wav.extend_from_slice(b"fmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&channels.to_le_bytes());
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    wav.extend_from_slice(&block_align.to_le_bytes());
+    wav.extend_from_slice(&bits.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_size.to_le_bytes());
+    for sample in samples {
+        let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
+
+fn js_error(e: JsValue) -> String {
+    format!("{:?}", e)
+}